@@ -0,0 +1,113 @@
+// Vertex adjacency of a triangle mesh, stored in compressed rows: the neighbors of vertex v are
+// neighbors[neighbor_offsets[v]..neighbor_offsets[v + 1]], sorted by index. The position of a
+// neighbor in that array identifies the directed edge, so per-edge data can be kept in a flat
+// array of the same length.
+pub(crate) struct Adjacency {
+    neighbor_offsets: Vec<usize>,
+    neighbors: Vec<usize>,
+    face_offsets: Vec<usize>,
+    faces: Vec<usize>,
+}
+
+// Pack rows into compressed form.
+fn compress(rows: Vec<Vec<usize>>) -> (Vec<usize>, Vec<usize>) {
+    let mut offsets = Vec::with_capacity(rows.len() + 1);
+    offsets.push(0);
+    let mut values = Vec::new();
+    for row in rows {
+        values.extend(row);
+        offsets.push(values.len());
+    }
+    (offsets, values)
+}
+
+impl Adjacency {
+    pub fn new(faces: &[[usize; 3]], num_vertices: usize) -> Adjacency {
+        let mut neighbors = vec![Vec::new(); num_vertices];
+        let mut vertex_faces = vec![Vec::new(); num_vertices];
+        for (i, f) in faces.iter().enumerate() {
+            for k in 0..3 {
+                let (a, b) = (f[k], f[(k + 1) % 3]);
+                neighbors[a].push(b);
+                neighbors[b].push(a);
+                vertex_faces[a].push(i);
+            }
+        }
+        for n in &mut neighbors {
+            n.sort_unstable();
+            n.dedup();
+        }
+        let (neighbor_offsets, neighbors) = compress(neighbors);
+        let (face_offsets, faces) = compress(vertex_faces);
+        Adjacency {
+            neighbor_offsets,
+            neighbors,
+            face_offsets,
+            faces,
+        }
+    }
+    pub fn num_vertices(&self) -> usize {
+        self.neighbor_offsets.len() - 1
+    }
+    pub fn neighbors(&self, v: usize) -> &[usize] {
+        &self.neighbors[self.edge_slot_range(v)]
+    }
+    pub fn faces(&self, v: usize) -> &[usize] {
+        &self.faces[self.face_offsets[v]..self.face_offsets[v + 1]]
+    }
+    // Total number of directed edges, i.e. the length of per-edge arrays.
+    pub fn num_directed_edges(&self) -> usize {
+        self.neighbors.len()
+    }
+    // Returns the slots of all directed edges starting at v in per-edge arrays, in the order of
+    // neighbors(v).
+    pub fn edge_slot_range(&self, v: usize) -> std::ops::Range<usize> {
+        self.neighbor_offsets[v]..self.neighbor_offsets[v + 1]
+    }
+    // Returns the slot of the directed edge a->b in per-edge arrays.
+    pub fn edge_slot(&self, a: usize, b: usize) -> Option<usize> {
+        let i = self.neighbors(a).binary_search(&b).ok()?;
+        Some(self.neighbor_offsets[a] + i)
+    }
+    // Returns the faces sharing the edge a-b.
+    pub fn edge_faces<'a>(
+        &'a self,
+        faces: &'a [[usize; 3]],
+        a: usize,
+        b: usize,
+    ) -> impl Iterator<Item = usize> + 'a {
+        self.faces(a)
+            .iter()
+            .copied()
+            .filter(move |&f| faces[f].contains(&b))
+    }
+    // Returns all edges a-b with a < b.
+    pub fn edges(&self) -> impl Iterator<Item = (usize, usize)> + '_ {
+        (0..self.num_vertices()).flat_map(move |a| {
+            self.neighbors(a)
+                .iter()
+                .copied()
+                .filter(move |&b| a < b)
+                .map(move |b| (a, b))
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tetrahedron() {
+        let faces = [[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 3, 2]];
+        let adjacency = Adjacency::new(&faces, 5);
+        assert_eq!(adjacency.neighbors(0), &[1, 2, 3]);
+        assert_eq!(adjacency.neighbors(4), &[] as &[usize]);
+        assert_eq!(adjacency.faces(3), &[1, 2, 3]);
+        assert_eq!(adjacency.edges().count(), 6);
+        assert_eq!(adjacency.edge_slot(1, 3), Some(5));
+        assert_eq!(adjacency.edge_slot(1, 4), None);
+        let shared: Vec<usize> = adjacency.edge_faces(&faces, 1, 2).collect();
+        assert_eq!(shared, vec![0, 2]);
+    }
+}
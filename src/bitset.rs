@@ -29,10 +29,6 @@ impl BitSet {
         self.0 == 0
     }
     #[cfg(test)]
-    pub fn from_u32(data: u32) -> BitSet {
-        BitSet(data)
-    }
-    #[cfg(test)]
     pub fn invert(self) -> BitSet {
         BitSet(!self.0)
     }
@@ -185,11 +181,11 @@ mod tests {
 
     #[test]
     fn empty() {
-        assert_eq!(super::BitSet(0b0000_0000).empty(), true);
-        assert_eq!(super::BitSet(0b1000_0000).empty(), false);
-        assert_eq!(super::BitSet(0b0100_1100).empty(), false);
-        assert_eq!(super::BitSet(0b1100_1101).empty(), false);
-        assert_eq!(super::BitSet(0b1111_1111).empty(), false);
+        assert!(super::BitSet(0b0000_0000).empty());
+        assert!(!super::BitSet(0b1000_0000).empty());
+        assert!(!super::BitSet(0b0100_1100).empty());
+        assert!(!super::BitSet(0b1100_1101).empty());
+        assert!(!super::BitSet(0b1111_1111).empty());
     }
 
     #[test]
@@ -0,0 +1,147 @@
+use crate::{ImplicitFunction, RealField};
+use bbox::BoundingBox;
+use nalgebra as na;
+use num_traits::Float;
+use std::fmt::Debug;
+
+/// Face tag of the faces on the cap of a [`ClipRegion`]. All other faces of a clipped mesh are
+/// tagged with 0.
+pub const CAP_FACE_TAG: u32 = u32::MAX;
+
+/// Region a tessellation is clipped to, see
+/// [`TessellationOptions::clip`](crate::TessellationOptions::clip).
+#[derive(Clone, Copy, Debug)]
+pub enum ClipRegion<S: RealField> {
+    /// Keep everything within the axis aligned box from `min` to `max`.
+    Box {
+        /// Minimum corner of the box.
+        min: na::Point3<S>,
+        /// Maximum corner of the box.
+        max: na::Point3<S>,
+    },
+    /// Keep everything below the plane through `point`, i.e. on the opposite side of `normal`.
+    HalfSpace {
+        /// Any point on the plane.
+        point: na::Point3<S>,
+        /// Normal of the plane, pointing away from the kept half.
+        normal: na::Vector3<S>,
+    },
+}
+
+impl<S: RealField + Float + From<f32>> ClipRegion<S> {
+    // Signed distance to the boundary of the region, negative inside.
+    pub(crate) fn value(&self, p: &na::Point3<S>) -> S {
+        let zero: S = From::from(0f32);
+        match *self {
+            ClipRegion::Box { min, max } => {
+                let half: S = From::from(0.5f32);
+                let q = (p - na::center(&min, &max)).abs() - (max - min) * half;
+                q.sup(&na::Vector3::zeros()).norm() + Float::min(q.max(), zero)
+            }
+            ClipRegion::HalfSpace { point, normal } => (p - point).dot(&normal.normalize()),
+        }
+    }
+    pub(crate) fn normal(&self, p: &na::Point3<S>) -> na::Vector3<S> {
+        let zero: S = From::from(0f32);
+        let one: S = From::from(1f32);
+        match *self {
+            ClipRegion::Box { min, max } => {
+                let half: S = From::from(0.5f32);
+                let d = p - na::center(&min, &max);
+                let q = d.abs() - (max - min) * half;
+                let n = if q.max() > zero {
+                    q.sup(&na::Vector3::zeros())
+                } else {
+                    let mut n = na::Vector3::zeros();
+                    n[q.imax()] = one;
+                    n
+                };
+                n.component_mul(&d.map(|c| if c < zero { -one } else { one }))
+                    .normalize()
+            }
+            ClipRegion::HalfSpace { normal, .. } => normal.normalize(),
+        }
+    }
+    // Bounds of the value within bbox. The value is a distance, so it changes by at most the
+    // distance from the center.
+    pub(crate) fn interval(&self, bbox: &BoundingBox<S, 3>) -> (S, S) {
+        let half: S = From::from(0.5f32);
+        let center = na::center(&bbox.min, &bbox.max);
+        let radius = (bbox.max - bbox.min).norm() * half;
+        let v = self.value(&center);
+        (v - radius, v + radius)
+    }
+}
+
+// The intersection of a function with a clip region.
+pub(crate) struct Clipped<'a, S: RealField> {
+    pub f: &'a dyn ImplicitFunction<S>,
+    pub clip: ClipRegion<S>,
+}
+
+impl<S: RealField + Float + From<f32> + Debug> ImplicitFunction<S> for Clipped<'_, S> {
+    fn value(&self, p: &na::Point3<S>) -> S {
+        Float::max(self.f.value(p), self.clip.value(p))
+    }
+    fn normal(&self, p: &na::Point3<S>) -> na::Vector3<S> {
+        if self.is_cap(p) {
+            self.clip.normal(p)
+        } else {
+            self.f.normal(p)
+        }
+    }
+    // All faces are tagged, so that the cap can be told apart.
+    fn tag(&self, p: &na::Point3<S>) -> Option<u32> {
+        if self.is_cap(p) {
+            Some(CAP_FACE_TAG)
+        } else {
+            Some(self.f.tag(p).unwrap_or(0))
+        }
+    }
+    fn lipschitz(&self) -> S {
+        Float::max(self.f.lipschitz(), From::from(1f32))
+    }
+    fn interval(&self, bbox: &BoundingBox<S, 3>) -> (S, S) {
+        let (lo, hi) = self.f.interval(bbox);
+        let max: S = Float::max_value();
+        if lo > -max || hi < max {
+            let (clip_lo, clip_hi) = self.clip.interval(bbox);
+            (Float::max(lo, clip_lo), Float::max(hi, clip_hi))
+        } else {
+            (lo, hi)
+        }
+    }
+}
+
+impl<S: RealField + Float + From<f32>> Clipped<'_, S> {
+    // Returns true, if the surface at p is formed by the clip region.
+    pub fn is_cap(&self, p: &na::Point3<S>) -> bool {
+        self.clip.value(p) > self.f.value(p)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn box_and_half_space() {
+        let clip = ClipRegion::Box {
+            min: na::Point3::new(-1., -1., -1.),
+            max: na::Point3::new(1., 2., 1.),
+        };
+        assert_eq!(clip.value(&na::Point3::new(0., 0., 0.)), -1.);
+        assert_eq!(clip.value(&na::Point3::new(0., 3., 0.)), 1.);
+        assert_eq!(clip.normal(&na::Point3::new(0., 1.5, 0.)), na::Vector3::new(0., 1., 0.));
+        let clip = ClipRegion::HalfSpace {
+            point: na::Point3::new(0., 0., 1.),
+            normal: na::Vector3::new(0., 0., 2.),
+        };
+        assert_eq!(clip.value(&na::Point3::new(5., 5., 3.)), 2.);
+        let (lo, hi) = clip.interval(&BoundingBox::new(
+            &na::Point3::new(0., 0., 0.),
+            &na::Point3::new(1., 1., 1.),
+        ));
+        assert!(lo <= -1. && hi >= 0.);
+    }
+}
@@ -0,0 +1,223 @@
+use crate::{
+    half_edge::{HalfEdgeError, HalfEdgeMesh},
+    mesh::{point, Mesh},
+    RealField,
+};
+use nalgebra as na;
+use num_traits::Float;
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+// Candidate edge collapse. Candidates are invalidated lazily: if either vertex changed since the
+// candidate was computed, its version does not match anymore and it is skipped.
+struct Collapse<S: RealField> {
+    cost: S,
+    a: usize,
+    b: usize,
+    position: na::Point3<S>,
+    versions: (usize, usize),
+}
+
+impl<S: RealField> PartialEq for Collapse<S> {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl<S: RealField> Eq for Collapse<S> {}
+
+impl<S: RealField> PartialOrd for Collapse<S> {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+// Reversed, so that BinaryHeap pops the cheapest collapse first. Ties are broken by the vertex
+// indexes to keep the result deterministic.
+impl<S: RealField> Ord for Collapse<S> {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .total_cmp(&self.cost)
+            .then_with(|| (other.a, other.b).cmp(&(self.a, self.b)))
+    }
+}
+
+// State of an ongoing decimation.
+struct Decimation<S: RealField> {
+    mesh: HalfEdgeMesh<S>,
+    quadrics: Vec<na::Matrix4<S>>,
+    versions: Vec<usize>,
+    num_faces: usize,
+    heap: BinaryHeap<Collapse<S>>,
+}
+
+impl<S: RealField + Float + From<f32>> Decimation<S> {
+    fn new(mesh: HalfEdgeMesh<S>) -> Self {
+        let n = mesh.num_vertices();
+        let mut quadrics = vec![na::Matrix4::zeros(); n];
+        for f in (0..mesh.num_faces()).filter_map(|f| mesh.face(f)) {
+            let [a, b, c] = f.map(|v| point(&mesh.position(v)));
+            let n = (b - a).cross(&(c - a));
+            // Weight the plane by the area of the face, so that slivers don't dominate.
+            let area = n.norm() * From::from(0.5f32);
+            if let Some(n) = n.try_normalize(S::zero()) {
+                let plane = na::Vector4::new(n.x, n.y, n.z, -n.dot(&a.coords));
+                let quadric = plane * plane.transpose() * area;
+                for v in f {
+                    quadrics[v] += quadric;
+                }
+            }
+        }
+        Decimation {
+            num_faces: mesh.num_faces(),
+            mesh,
+            quadrics,
+            versions: vec![0; n],
+            heap: BinaryHeap::new(),
+        }
+    }
+    fn position(&self, v: usize) -> na::Point3<S> {
+        point(&self.mesh.position(v))
+    }
+    fn cost(&self, q: &na::Matrix4<S>, p: &na::Point3<S>) -> S {
+        let p = p.to_homogeneous();
+        (p.transpose() * q * p)[0]
+    }
+    // Push the best collapse of the edge a-b onto the heap. Vertices on the boundary of an open
+    // mesh are never moved, so the boundary is kept.
+    fn push_candidate(&mut self, a: usize, b: usize) {
+        if self.mesh.is_boundary_vertex(a) || self.mesh.is_boundary_vertex(b) {
+            return;
+        }
+        let q = self.quadrics[a] + self.quadrics[b];
+        let (pa, pb) = (self.position(a), self.position(b));
+        let mid = na::center(&pa, &pb);
+        // Solve for the point minimizing the quadric. Near flat regions the system is almost
+        // singular and the optimum runs off, so it is only used close to the edge.
+        let optimum = q
+            .fixed_view::<3, 3>(0, 0)
+            .into_owned()
+            .try_inverse()
+            .map(|inverse| na::Point3::from(-(inverse * q.fixed_view::<3, 1>(0, 3))))
+            .filter(|p| (p - mid).norm() <= (pb - pa).norm());
+        let (position, cost) = optimum
+            .into_iter()
+            .chain([mid, pa, pb])
+            .map(|p| (p, self.cost(&q, &p)))
+            .min_by(|x, y| x.1.total_cmp(&y.1))
+            .unwrap();
+        self.heap.push(Collapse {
+            cost,
+            a,
+            b,
+            position,
+            versions: (self.versions[a], self.versions[b]),
+        });
+    }
+    // Returns whether moving a and b to position keeps all faces, but the ones of the edge a-b,
+    // from flipping.
+    fn keeps_orientation(&self, a: usize, b: usize, position: &na::Point3<S>) -> bool {
+        [a, b].iter().all(|&v| {
+            self.mesh.vertex_faces(v).into_iter().all(|f| {
+                let face = self.mesh.face(f).unwrap();
+                if face.contains(&a) && face.contains(&b) {
+                    return true;
+                }
+                let corners = face.map(|u| self.position(u));
+                let moved = face.map(|u| {
+                    if u == a || u == b {
+                        *position
+                    } else {
+                        self.position(u)
+                    }
+                });
+                let before = (corners[1] - corners[0]).cross(&(corners[2] - corners[0]));
+                let after = (moved[1] - moved[0]).cross(&(moved[2] - moved[0]));
+                before.dot(&after) > S::zero()
+            })
+        })
+    }
+    // Collapse b into a at position, unless that breaks the 2-manifold property or flips a face.
+    fn collapse(&mut self, a: usize, b: usize, position: na::Point3<S>) {
+        let Some(h) = self.mesh.half_edge(b, a) else {
+            return;
+        };
+        if !self.keeps_orientation(a, b, &position)
+            || !self.mesh.collapse(h, [position.x, position.y, position.z])
+        {
+            return;
+        }
+        self.num_faces -= 2;
+        self.quadrics[a] = self.quadrics[a] + self.quadrics[b];
+        // Only the costs of the edges of a changed.
+        for v in [a, b] {
+            self.versions[v] += 1;
+        }
+        for n in self.mesh.one_ring(a) {
+            self.push_candidate(a.min(n), a.max(n));
+        }
+    }
+    fn run(&mut self, target: usize) {
+        for a in 0..self.mesh.num_vertices() {
+            for b in self.mesh.one_ring(a) {
+                if a < b {
+                    self.push_candidate(a, b);
+                }
+            }
+        }
+        while self.num_faces > target {
+            let Some(c) = self.heap.pop() else {
+                break;
+            };
+            if (self.versions[c.a], self.versions[c.b]) == c.versions {
+                self.collapse(c.a, c.b, c.position);
+            }
+        }
+    }
+}
+
+impl<S: RealField + Float + From<f32>> Mesh<S> {
+    /// Reduce the mesh to at most `target` faces by collapsing edges in order of increasing
+    /// quadric error (Garland and Heckbert).
+    ///
+    /// Collapses that would break the 2-manifold property (link condition) or flip a face are
+    /// skipped, so a closed manifold stays closed and manifold and the target might not be met.
+    /// The boundary of open meshes is kept. Face tags and the attributes of the surviving
+    /// vertices are kept as well. Fails and leaves the mesh unchanged, if it is not an oriented
+    /// 2-manifold.
+    pub fn decimate(&mut self, target: usize) -> Result<(), HalfEdgeError> {
+        let mut decimation = Decimation::new(HalfEdgeMesh::from_mesh(self)?);
+        decimation.run(target);
+        *self = decimation.mesh.to_mesh();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_shapes::Sphere, ManifoldDualContouring};
+    use nalgebra as na;
+
+    #[test]
+    fn decimate_sphere() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mesh = ManifoldDualContouring::new(&Sphere::unit(), 0.05, 0.)
+            .tessellate()
+            .unwrap();
+        let faces = mesh.faces.len();
+        assert!(faces > 5000);
+        mesh.face_tags = (0..faces as u32).collect();
+        mesh.decimate(500)?;
+        assert!(mesh.faces.len() <= 500 && mesh.faces.len() > 400, "{}", mesh.faces.len());
+        mesh.is_closed()?;
+        assert_eq!(mesh.face_tags.len(), mesh.faces.len());
+        for v in &mesh.vertices {
+            assert!((na::Vector3::from(*v).norm() - 1.).abs() < 0.02, "{v:?}");
+        }
+        // No face got flipped inside out.
+        for f in &mesh.faces {
+            let [a, b, c] = f.map(|i| na::Vector3::from(mesh.vertices[i]));
+            assert!((b - a).cross(&(c - a)).dot(&(a + b + c)) > 0.);
+        }
+        Ok(())
+    }
+}
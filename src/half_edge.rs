@@ -0,0 +1,478 @@
+use crate::mesh::{Mesh, MeshSink};
+use std::{collections::HashMap, fmt};
+
+/// Reason why a [`Mesh`] can not be converted into a [`HalfEdgeMesh`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HalfEdgeError {
+    /// The directed edge from the first to the second vertex is used by more than one face, i.e.
+    /// the edge has more than two faces or its faces are oriented inconsistently.
+    NonManifoldEdge(usize, usize),
+    /// The faces around the vertex do not form a single fan.
+    NonManifoldVertex(usize),
+    /// The face uses a vertex more than once.
+    DegenerateFace(usize),
+}
+
+impl fmt::Display for HalfEdgeError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            HalfEdgeError::NonManifoldEdge(a, b) => write!(f, "edge {}->{} is not manifold", a, b),
+            HalfEdgeError::NonManifoldVertex(v) => write!(f, "vertex {} is not manifold", v),
+            HalfEdgeError::DegenerateFace(i) => write!(f, "face {} is degenerate", i),
+        }
+    }
+}
+
+impl std::error::Error for HalfEdgeError {}
+
+/// Half-edge view of a manifold triangle [`Mesh`] with adjacency queries and local edits.
+///
+/// Half-edges are identified by `3 * face + k`, the half-edge from corner `k` to corner `k + 1`
+/// of `face`. Faces and vertices keep the indexes they have in the mesh. Removed faces and
+/// vertices are only dropped by [`to_mesh`](Self::to_mesh), which also compacts the indexes.
+///
+/// In debug builds all edits check the invariants of the half-edges they touched.
+#[derive(Clone, Debug)]
+pub struct HalfEdgeMesh<S> {
+    mesh: Mesh<S>,
+    // Map from the vertices of each directed edge to its half-edge.
+    half_edges: HashMap<(usize, usize), usize>,
+    // Some outgoing half-edge of each vertex, None for isolated or removed vertices.
+    outgoing: Vec<Option<usize>>,
+    face_removed: Vec<bool>,
+}
+
+impl<S: Copy> HalfEdgeMesh<S> {
+    /// Build the half-edges of `mesh`. Fails, if mesh is not an oriented 2-manifold, possibly
+    /// with boundary.
+    pub fn from_mesh(mesh: &Mesh<S>) -> Result<HalfEdgeMesh<S>, HalfEdgeError> {
+        let mut result = HalfEdgeMesh {
+            mesh: mesh.clone(),
+            half_edges: HashMap::with_capacity(3 * mesh.faces.len()),
+            outgoing: vec![None; mesh.vertices.len()],
+            face_removed: vec![false; mesh.faces.len()],
+        };
+        for (i, f) in mesh.faces.iter().enumerate() {
+            if f[0] == f[1] || f[1] == f[2] || f[2] == f[0] {
+                return Err(HalfEdgeError::DegenerateFace(i));
+            }
+            for k in 0..3 {
+                let (a, b) = (f[k], f[(k + 1) % 3]);
+                if result.half_edges.insert((a, b), 3 * i + k).is_some() {
+                    return Err(HalfEdgeError::NonManifoldEdge(a, b));
+                }
+                result.outgoing[a] = Some(3 * i + k);
+            }
+        }
+        // Every vertex must have a single fan containing all of its outgoing half-edges.
+        let mut degree = vec![0; mesh.vertices.len()];
+        for f in &mesh.faces {
+            f.iter().for_each(|&v| degree[v] += 1);
+        }
+        for (v, &degree) in degree.iter().enumerate() {
+            if degree > 0 && result.fan(v).len() != degree {
+                return Err(HalfEdgeError::NonManifoldVertex(v));
+            }
+        }
+        Ok(result)
+    }
+    /// Return the mesh with all removed faces and vertices dropped. Vertices without faces are
+    /// dropped as well.
+    pub fn to_mesh(&self) -> Mesh<S> {
+        let mut mesh = self.mesh.clone();
+        mesh.remove_faces(&self.face_removed);
+        mesh
+    }
+    /// Position of vertex `v`.
+    pub fn position(&self, v: usize) -> [S; 3] {
+        self.mesh.vertices[v]
+    }
+    /// Move vertex `v` to `position`.
+    pub fn set_position(&mut self, v: usize, position: [S; 3]) {
+        self.mesh.vertices[v] = position;
+    }
+    /// Number of vertex slots, including removed vertices.
+    pub fn num_vertices(&self) -> usize {
+        self.mesh.vertices.len()
+    }
+    /// Number of face slots, including removed faces.
+    pub fn num_faces(&self) -> usize {
+        self.mesh.faces.len()
+    }
+    /// Vertices of `face` or None, if it was removed.
+    pub fn face(&self, face: usize) -> Option<[usize; 3]> {
+        (!self.face_removed[face]).then(|| self.mesh.faces[face])
+    }
+    /// Tag of `face`, if the mesh has face tags.
+    pub fn face_tag(&self, face: usize) -> Option<u32> {
+        self.mesh.face_tags.get(face).copied()
+    }
+    /// Vertex half-edge `h` starts at.
+    pub fn from(&self, h: usize) -> usize {
+        self.mesh.faces[h / 3][h % 3]
+    }
+    /// Vertex half-edge `h` points to.
+    pub fn to(&self, h: usize) -> usize {
+        self.mesh.faces[h / 3][(h + 1) % 3]
+    }
+    /// Next half-edge in the face of `h`.
+    pub fn next(&self, h: usize) -> usize {
+        h - h % 3 + (h + 1) % 3
+    }
+    /// Previous half-edge in the face of `h`.
+    pub fn prev(&self, h: usize) -> usize {
+        h - h % 3 + (h + 2) % 3
+    }
+    /// Half-edge in the opposite direction or None, if `h` is on the boundary.
+    pub fn twin(&self, h: usize) -> Option<usize> {
+        self.half_edges.get(&(self.to(h), self.from(h))).copied()
+    }
+    /// Half-edge from `a` to `b`, if there is one.
+    pub fn half_edge(&self, a: usize, b: usize) -> Option<usize> {
+        self.half_edges.get(&(a, b)).copied()
+    }
+    // Returns the outgoing half-edges of v in counter-clockwise order. On the boundary the first
+    // one has no twin.
+    fn fan(&self, v: usize) -> Vec<usize> {
+        let Some(start) = self.outgoing[v] else {
+            return Vec::new();
+        };
+        // Turn clockwise until the boundary or the start is reached.
+        let mut first = start;
+        while let Some(twin) = self.twin(first) {
+            first = self.next(twin);
+            if first == start {
+                break;
+            }
+        }
+        let mut fan = vec![first];
+        while let Some(h) = self.twin(self.prev(*fan.last().unwrap())) {
+            if h == first {
+                break;
+            }
+            fan.push(h);
+        }
+        fan
+    }
+    /// Returns whether `v` is on the boundary.
+    pub fn is_boundary_vertex(&self, v: usize) -> bool {
+        self.outgoing[v].is_some() && self.twin(self.fan(v)[0]).is_none()
+    }
+    /// Neighbors of `v` in counter-clockwise order, starting at the boundary, if `v` is on it.
+    pub fn one_ring(&self, v: usize) -> Vec<usize> {
+        let fan = self.fan(v);
+        let mut ring: Vec<usize> = fan.iter().map(|&h| self.to(h)).collect();
+        if let Some(&last) = fan.last().filter(|&&h| self.twin(self.prev(h)).is_none()) {
+            ring.push(self.from(self.prev(last)));
+        }
+        ring
+    }
+    /// Faces around `v` in counter-clockwise order.
+    pub fn vertex_faces(&self, v: usize) -> Vec<usize> {
+        self.fan(v).iter().map(|h| h / 3).collect()
+    }
+    /// Faces across the three edges of `face`. None on the boundary.
+    pub fn face_neighbors(&self, face: usize) -> [Option<usize>; 3] {
+        std::array::from_fn(|k| self.twin(3 * face + k).map(|h| h / 3))
+    }
+    /// All boundary loops as lists of vertices, in the direction of their half-edges and starting
+    /// at their smallest vertex.
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let mut boundary: Vec<usize> = self
+            .half_edges
+            .values()
+            .copied()
+            .filter(|&h| self.twin(h).is_none())
+            .collect();
+        boundary.sort_unstable();
+        let mut visited = vec![false; 3 * self.num_faces()];
+        let mut loops = Vec::new();
+        for start in boundary {
+            if visited[start] {
+                continue;
+            }
+            let mut boundary_loop = Vec::new();
+            let mut h = start;
+            while !visited[h] {
+                visited[h] = true;
+                boundary_loop.push(self.from(h));
+                // Turn around the end of h until the next boundary half-edge.
+                h = self.next(h);
+                while let Some(twin) = self.twin(h) {
+                    h = self.next(twin);
+                }
+            }
+            let first = (0..boundary_loop.len()).min_by_key(|&i| boundary_loop[i]).unwrap();
+            boundary_loop.rotate_left(first);
+            loops.push(boundary_loop);
+        }
+        loops
+    }
+    fn remove_face(&mut self, face: usize) {
+        let f = self.mesh.faces[face];
+        for k in 0..3 {
+            self.half_edges.remove(&(f[k], f[(k + 1) % 3]));
+        }
+        self.face_removed[face] = true;
+    }
+    fn set_face(&mut self, face: usize, f: [usize; 3]) {
+        self.mesh.faces[face] = f;
+        self.face_removed[face] = false;
+        for k in 0..3 {
+            self.half_edges.insert((f[k], f[(k + 1) % 3]), 3 * face + k);
+            self.outgoing[f[k]] = Some(3 * face + k);
+        }
+    }
+    fn add_face(&mut self, f: [usize; 3], tag: Option<u32>) -> usize {
+        let face = self.mesh.faces.len();
+        match tag {
+            Some(tag) => self.mesh.add_tagged_triangle(f, tag),
+            None => self.mesh.add_triangle(f),
+        }
+        self.face_removed.push(false);
+        self.set_face(face, f);
+        face
+    }
+    /// Flip the interior edge of `h`, so that it connects the two opposite vertices of its faces.
+    /// Returns false and leaves the mesh unchanged, if the edge is on the boundary or the
+    /// opposite vertices are already connected.
+    pub fn flip(&mut self, h: usize) -> bool {
+        let Some(twin) = self.twin(h) else {
+            return false;
+        };
+        let (a, b) = (self.from(h), self.to(h));
+        let c = self.to(self.next(h));
+        let d = self.to(self.next(twin));
+        if self.half_edge(c, d).is_some() || self.one_ring(a).len() <= 3 {
+            return false;
+        }
+        if self.one_ring(b).len() <= 3 {
+            return false;
+        }
+        let (f1, f2) = (h / 3, twin / 3);
+        self.remove_face(f1);
+        self.remove_face(f2);
+        self.set_face(f1, [a, d, c]);
+        self.set_face(f2, [d, b, c]);
+        self.outgoing[a] = Some(3 * f1);
+        self.outgoing[b] = Some(3 * f2 + 1);
+        self.debug_check(&[a, b, c, d]);
+        true
+    }
+    /// Split the edge of `h` by inserting a new vertex at `position` and return it. Each face of
+    /// the edge is split in two. Vertex attributes of the new vertex are interpolated.
+    pub fn split(&mut self, h: usize, position: [S; 3]) -> usize {
+        let (a, b) = (self.from(h), self.to(h));
+        let m = self.mesh.add_vertex(position);
+        let stride = self.mesh.attribute_stride;
+        let attributes = &mut self.mesh.vertex_attributes;
+        for k in 0..stride {
+            let (x, y) = (attributes[a * stride + k], attributes[b * stride + k]);
+            attributes[m * stride + k] = (x + y) * 0.5;
+        }
+        self.outgoing.push(None);
+        let faces: Vec<usize> = [Some(h), self.twin(h)].into_iter().flatten().collect();
+        let mut touched = vec![a, b, m];
+        for h in faces {
+            let face = h / 3;
+            let (u, w, c) = (self.from(h), self.to(h), self.to(self.next(h)));
+            let tag = self.mesh.face_tags.get(face).copied();
+            self.remove_face(face);
+            self.set_face(face, [u, m, c]);
+            self.add_face([m, w, c], tag);
+            // u and w might have pointed at the removed half-edges.
+            self.outgoing[u] = Some(3 * face);
+            self.outgoing[w] = self.half_edge(w, c).or(self.half_edge(w, m));
+            touched.push(c);
+        }
+        self.debug_check(&touched);
+        m
+    }
+    /// Collapse the edge of `h` by removing its start vertex and moving its end vertex to
+    /// `position`. Returns false and leaves the mesh unchanged, if the result would not be a
+    /// 2-manifold anymore (link condition) or if an opposite vertex would keep only two
+    /// neighbors.
+    pub fn collapse(&mut self, h: usize, position: [S; 3]) -> bool {
+        let (a, b) = (self.from(h), self.to(h));
+        let faces: Vec<usize> = [Some(h), self.twin(h)].into_iter().flatten().collect();
+        let opposite: Vec<usize> = faces.iter().map(|&h| self.to(self.next(h))).collect();
+        let ring_a = self.one_ring(a);
+        let ring_b = self.one_ring(b);
+        let common = ring_a.iter().filter(|v| ring_b.contains(v)).count();
+        if common != opposite.len() {
+            return false;
+        }
+        // An interior edge between two boundary vertices would pinch the surface.
+        if faces.len() == 2 && self.is_boundary_vertex(a) && self.is_boundary_vertex(b) {
+            return false;
+        }
+        if opposite.iter().any(|&v| self.one_ring(v).len() <= 3) {
+            return false;
+        }
+        let fan = self.fan(a);
+        for h in faces.iter().map(|h| h / 3) {
+            self.remove_face(h);
+        }
+        for h in fan {
+            let face = h / 3;
+            if self.face_removed[face] {
+                continue;
+            }
+            let f = self.mesh.faces[face].map(|v| if v == a { b } else { v });
+            self.remove_face(face);
+            self.set_face(face, f);
+        }
+        self.outgoing[a] = None;
+        self.mesh.vertices[b] = position;
+        // The outgoing half-edges of b and the opposite vertices might have been removed.
+        for &v in opposite.iter().chain([&b]) {
+            self.outgoing[v] = ring_a
+                .iter()
+                .chain(&ring_b)
+                .find_map(|&u| self.half_edge(v, u));
+        }
+        let mut touched = opposite;
+        touched.push(b);
+        self.debug_check(&touched);
+        true
+    }
+    // Check the invariants of the half-edges around vertices in debug builds.
+    fn debug_check(&self, vertices: &[usize]) {
+        if cfg!(debug_assertions) {
+            for &v in vertices {
+                let fan = self.fan(v);
+                // Edits only change half-edges between the touched vertices.
+                for &u in vertices {
+                    if let Some(h) = self.half_edge(v, u) {
+                        debug_assert!(fan.contains(&h), "vertex {} is not manifold", v);
+                    }
+                }
+                for h in fan {
+                    debug_assert!(!self.face_removed[h / 3]);
+                    debug_assert_eq!(self.from(h), v);
+                    debug_assert_eq!(self.half_edge(v, self.to(h)), Some(h));
+                    if let Some(twin) = self.twin(h) {
+                        debug_assert_eq!(self.twin(twin), Some(h));
+                    }
+                }
+            }
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn indexed_mesh(vertices: Vec<[f64; 3]>, faces: Vec<[usize; 3]>) -> Mesh<f64> {
+        Mesh {
+            vertices,
+            faces,
+            ..Mesh::default()
+        }
+    }
+
+    // Square made of two triangles.
+    fn square() -> Mesh<f64> {
+        indexed_mesh(
+            vec![[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [0., 1., 0.]],
+            vec![[0, 1, 2], [0, 2, 3]],
+        )
+    }
+
+    fn octahedron() -> Mesh<f64> {
+        indexed_mesh(
+            vec![
+                [1., 0., 0.],
+                [-1., 0., 0.],
+                [0., 1., 0.],
+                [0., -1., 0.],
+                [0., 0., 1.],
+                [0., 0., -1.],
+            ],
+            vec![
+                [0, 2, 4],
+                [2, 1, 4],
+                [1, 3, 4],
+                [3, 0, 4],
+                [2, 0, 5],
+                [1, 2, 5],
+                [3, 1, 5],
+                [0, 3, 5],
+            ],
+        )
+    }
+
+    #[test]
+    fn queries() {
+        let mesh = HalfEdgeMesh::from_mesh(&square()).unwrap();
+        assert_eq!(mesh.one_ring(0), vec![1, 2, 3]);
+        assert_eq!(mesh.one_ring(2), vec![3, 0, 1]);
+        assert!(mesh.is_boundary_vertex(0));
+        assert_eq!(mesh.face_neighbors(0), [None, None, Some(1)]);
+        assert_eq!(mesh.boundary_loops(), vec![vec![0, 1, 2, 3]]);
+
+        let mesh = HalfEdgeMesh::from_mesh(&octahedron()).unwrap();
+        // Interior rings start anywhere.
+        let mut ring = mesh.one_ring(4);
+        let first = ring.iter().position(|&v| v == 0).unwrap();
+        ring.rotate_left(first);
+        assert_eq!(ring, vec![0, 2, 1, 3]);
+        assert!(!mesh.is_boundary_vertex(4));
+        assert!(mesh.boundary_loops().is_empty());
+        assert_eq!(mesh.to_mesh(), octahedron());
+    }
+
+    #[test]
+    fn non_manifold() {
+        let edge = indexed_mesh(square().vertices, vec![[0, 1, 2], [0, 2, 3], [0, 1, 3]]);
+        assert_eq!(
+            HalfEdgeMesh::from_mesh(&edge).unwrap_err(),
+            HalfEdgeError::NonManifoldEdge(0, 1)
+        );
+        // Two triangles touching at vertex 0 only.
+        let vertex = indexed_mesh(
+            vec![[0., 0., 0.], [1., 0., 0.], [1., 1., 0.], [-1., 0., 0.], [0., -1., 0.]],
+            vec![[0, 1, 2], [0, 3, 4]],
+        );
+        assert_eq!(
+            HalfEdgeMesh::from_mesh(&vertex).unwrap_err(),
+            HalfEdgeError::NonManifoldVertex(0)
+        );
+    }
+
+    #[test]
+    fn flip_split_collapse() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mesh = HalfEdgeMesh::from_mesh(&octahedron())?;
+        assert!(mesh.flip(mesh.half_edge(0, 4).unwrap()));
+        assert!(mesh.half_edge(0, 4).is_none());
+        assert!(mesh.half_edge(2, 3).is_some());
+        mesh.to_mesh().is_closed()?;
+        let m = mesh.split(mesh.half_edge(2, 3).unwrap(), [0.5, 0., 0.]);
+        assert_eq!(mesh.one_ring(m).len(), 4);
+        let split = mesh.to_mesh();
+        assert_eq!(split.faces.len(), 10);
+        split.is_closed()?;
+        assert!(mesh.collapse(mesh.half_edge(m, 2).unwrap(), [0., 1., 0.]));
+        let collapsed = mesh.to_mesh();
+        assert_eq!(collapsed.faces.len(), 8);
+        assert_eq!(collapsed.vertices.len(), 6);
+        collapsed.is_closed()?;
+
+        // Collapsing an edge of a tetrahedron would leave two faces back to back.
+        let tetrahedron = indexed_mesh(
+            vec![[1., 0., 0.], [-1., 0., 0.], [0., 1., 0.], [0., -1., 0.]],
+            vec![[0, 2, 1], [0, 1, 3], [1, 2, 3], [0, 3, 2]],
+        );
+        let mut tetrahedron = HalfEdgeMesh::from_mesh(&tetrahedron)?;
+        assert!(!tetrahedron.collapse(0, [0., 0., 0.]));
+        assert!(!tetrahedron.flip(0));
+
+        let mut square = HalfEdgeMesh::from_mesh(&square())?;
+        assert!(!square.flip(0));
+        square.split(square.half_edge(0, 2).unwrap(), [0.5, 0.5, 0.]);
+        assert_eq!(square.boundary_loops(), vec![vec![0, 1, 2, 3]]);
+        assert_eq!(square.to_mesh().faces.len(), 4);
+        Ok(())
+    }
+}
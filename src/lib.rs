@@ -24,25 +24,92 @@
 //! let mut mdc = tessellation::ManifoldDualContouring::new(&sphere, 0.2, 0.1);
 //! let triangles = mdc.tessellate().unwrap();
 //! ```
+//!
+//! For quick previews [`SurfaceNets`] produces a coarser looking mesh a lot faster:
+//!
+//! ```rust
+//! # use nalgebra as na;
+//! # struct UnitSphere;
+//! # impl tessellation::ImplicitFunction<f64> for UnitSphere {
+//! #   fn value(&self, p: &na::Point3<f64>) -> f64 {
+//! #     na::Vector3::new(p.x, p.y, p.z).norm() - 1.0
+//! #   }
+//! #   fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+//! #     na::Vector3::new(p.x, p.y, p.z).normalize()
+//! #   }
+//! # }
+//! # let sphere = UnitSphere;
+//! let mut sn = tessellation::SurfaceNets::new(&sphere, 0.2);
+//! let preview = sn.tessellate().unwrap();
+//! ```
+//!
+//! Large meshes can be written to a file while they are generated, without collecting them in a
+//! [`Mesh`] first:
+//!
+//! ```rust,no_run
+//! # use nalgebra as na;
+//! # struct UnitSphere;
+//! # impl tessellation::ImplicitFunction<f64> for UnitSphere {
+//! #   fn value(&self, p: &na::Point3<f64>) -> f64 {
+//! #     na::Vector3::new(p.x, p.y, p.z).norm() - 1.0
+//! #   }
+//! #   fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+//! #     na::Vector3::new(p.x, p.y, p.z).normalize()
+//! #   }
+//! # }
+//! # let sphere = UnitSphere;
+//! let file = std::io::BufWriter::new(std::fs::File::create("sphere.stl")?);
+//! let mut stl = tessellation::StlWriter::new(file)?;
+//! let mut mdc = tessellation::ManifoldDualContouring::new(&sphere, 0.2, 0.1);
+//! mdc.tessellate_into(&mut stl, |_| {});
+//! stl.finish()?;
+//! # Ok::<(), std::io::Error>(())
+//! ```
 #![warn(missing_docs)]
 
 use nalgebra as na;
 use std::fmt::Debug;
 
+mod adjacency;
 mod bitset;
 mod cell_configs;
+mod clip;
+mod decimate;
+mod half_edge;
 mod manifold_dual_contouring;
 mod mesh;
+mod multi_material;
+mod obj;
+mod options;
 mod plane;
+mod ply;
 mod qef;
+mod remesh;
+mod smooth;
+mod sparse_grid;
+mod stl;
+mod surface_nets;
+#[cfg(test)]
+mod test_shapes;
 mod vertex_index;
 
+pub use self::clip::{ClipRegion, CAP_FACE_TAG};
+pub use self::half_edge::{HalfEdgeError, HalfEdgeMesh};
+pub use self::manifold_dual_contouring::LevelOfDetail;
 pub use self::manifold_dual_contouring::ManifoldDualContouring;
 pub use self::manifold_dual_contouring::ProgressEvent;
-pub use self::mesh::Mesh;
+pub use self::manifold_dual_contouring::RetessellateError;
+pub use self::mesh::{Mesh, MeshSink};
+pub use self::multi_material::MultiMaterial;
+pub use self::options::TessellationOptions;
+pub use self::ply::PlyWriter;
+pub use self::smooth::Laplacian;
+pub use self::stl::StlWriter;
+pub use self::surface_nets::SurfaceNets;
+pub use bbox::BoundingBox;
 
-/// Trait alias for nalgebra's RealField.
-pub trait RealField: na::RealField + Copy {}
+/// Trait alias for nalgebra's RealField, with a total order.
+pub trait RealField: na::RealField + Copy + num_traits::float::TotalOrder {}
 impl RealField for f64 {}
 impl RealField for f32 {}
 
@@ -51,11 +118,40 @@ pub trait ImplicitFunction<S: Debug + RealField> {
     /// Evaluate the function on p and return the value. A value of zero signifies that p is on the
     /// surface to be tessellated. A negative value means p in inside the object. A positive value
     /// means p is outside the object.
-    /// The magnitude of value must be continuous. Furthermore value has to be equal or greater
-    /// than the euclidean distance between p and the surface.
+    /// The magnitude of value must be continuous. Furthermore value divided by
+    /// [`lipschitz`](Self::lipschitz) must not exceed the euclidean distance between p and the
+    /// surface. The bounds of the surface are searched with this distance bound, so
+    /// [`lipschitz`](Self::lipschitz) is the only way to relax it.
     fn value(&self, p: &na::Point3<S>) -> S;
     /// Compute the normal of the function at p.
     fn normal(&self, p: &na::Point3<S>) -> na::Vector3<S>;
+    /// Return a Lipschitz constant of the function, i.e. an upper bound of the length of its
+    /// gradient. Default: 1, which is correct for signed distance functions.
+    ///
+    /// Override this for fields that overestimate the distance, like smooth blends, displacements
+    /// or scaled fields. Values are divided by it to get a distance bound.
+    fn lipschitz(&self) -> S {
+        S::one()
+    }
+    /// Return lower and upper bounds of the function values within bbox, e.g. computed with
+    /// interval arithmetic.
+    ///
+    /// If implemented, the sampler uses the bounds instead of the distance property of
+    /// [`value`](Self::value) to decide whether a region of the grid can contain the surface, so
+    /// the bounds must be conservative. The search for the bounds of the surface does not use
+    /// them. The default returns the unbounded interval, which means unknown.
+    fn interval(&self, _bbox: &BoundingBox<S, 3>) -> (S, S) {
+        let max = S::max_value().unwrap();
+        (-max, max)
+    }
+    /// Return a tag for the surface at p, e.g. the id of the CSG primitive closest to p.
+    /// Default: `None`, which leaves the faces untagged.
+    ///
+    /// The tag is evaluated at every edge crossing. Each face gets the most frequent tag of the
+    /// crossings around it in [`Mesh::face_tags`].
+    fn tag(&self, _p: &na::Point3<S>) -> Option<u32> {
+        None
+    }
 }
 
 /// Trait which allows to convert Self to usize, since To<usize> is not implemented by f32 and f64.
@@ -1,9 +1,13 @@
 use crate::{
     bitset::BitSet,
     cell_configs::CELL_CONFIGS,
-    mesh::Mesh,
+    clip::Clipped,
+    mesh::{Mesh, MeshSink},
+    options::TessellationOptions,
     plane::Plane,
-    qef,
+    qef::{self, EPSILON},
+    remesh::project_to_surface,
+    sparse_grid::{FastHashMap, GridKey, SparseGrid, GRID_LIMIT},
     vertex_index::{neg_offset, offset, Index, VarIndex, VertexIndex, EDGES_ON_FACE},
     AsUSize, ImplicitFunction, RealField,
 };
@@ -14,12 +18,14 @@ use rayon::prelude::*;
 use std::{
     cell::{Cell, RefCell},
     cmp,
-    collections::{BTreeSet, HashMap},
-    error, fmt,
+    collections::{BTreeSet, HashMap, HashSet},
+    fmt,
+    sync::Arc,
 };
 
-// How accurately find zero crossings.
-const PRECISION: f32 = 0.05;
+// Smallest sub-cube size (in cells) adaptive sampling tests for flatness. The flatness test
+// evaluates the function nine times, refining a sub-cube of size 2 needs only seven.
+const MIN_FLAT_SIZE: usize = 4;
 
 //  Edge indexes
 //
@@ -113,19 +119,12 @@ static OUTSIDE_EDGES_PER_CORNER: LazyLock<[BitSet; 8]> = LazyLock::new(|| {
     ]
 });
 
-#[derive(Debug)]
-pub enum DualContouringError {
-    HitZero(String),
-}
-
-impl error::Error for DualContouringError {}
-
-impl fmt::Display for DualContouringError {
-    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
-        match *self {
-            DualContouringError::HitZero(ref s) => write!(f, "Hit zero value for {}", s),
-        }
-    }
+// Returns whether a sampled value is inside the object.
+// Exact zeros are resolved by symbolic perturbation: zero is treated as a tiny positive value,
+// i.e. points on the surface count as outside. Applying this rule consistently in sampling, cell
+// classification and root finding lets grids with exact zeros be tessellated as they are.
+pub fn is_inside<S: RealField + From<f32>>(value: S) -> bool {
+    value < From::from(0f32)
 }
 
 // A vertex of the mesh. This can be either a primary vertex of the sampled mesh or a vertex
@@ -141,6 +140,11 @@ pub struct Vertex<S: RealField> {
     mesh_index: Cell<Option<usize>>,
     edge_intersections: [u32; 12],
     euler_characteristic: i32,
+    // Pinned vertices are never merged into their parent. Used for vertices shared between chunks.
+    pinned: bool,
+    // Vertices replaced by retessellate_region stay in their layer, so that the indexes of all
+    // others remain valid. Nothing refers to them anymore.
+    removed: bool,
 }
 
 impl<S: RealField> Clone for Vertex<S> {
@@ -161,6 +165,8 @@ impl<S: RealField> Clone for Vertex<S> {
             mesh_index: self.mesh_index.clone(),
             edge_intersections: self.edge_intersections,
             euler_characteristic: self.euler_characteristic,
+            pinned: self.pinned,
+            removed: self.removed,
         }
     }
 }
@@ -185,8 +191,8 @@ impl<S: RealField> Vertex<S> {
 
 #[derive(Clone, Copy, Debug, Eq, Hash, PartialEq)]
 pub struct EdgeIndex {
-    edge: Edge,
-    index: Index,
+    pub edge: Edge,
+    pub index: Index,
 }
 
 impl EdgeIndex {
@@ -198,22 +204,112 @@ impl EdgeIndex {
     }
 }
 
+// Edges are stored in a SparseGrid by interleaving the three base edges along the x axis.
+impl GridKey for EdgeIndex {
+    fn to_cell(self) -> [u32; 3] {
+        debug_assert!((self.edge as usize) < 3);
+        let [x, y, z] = self.index.to_cell();
+        [x * 3 + self.edge as u32, y, z]
+    }
+    fn from_cell(cell: [u32; 3]) -> Self {
+        EdgeIndex {
+            edge: Edge::from_usize((cell[0] % 3) as usize),
+            index: Index::from_cell([cell[0] / 3, cell[1], cell[2]]),
+        }
+    }
+}
+
+// Crossing of the surface with a grid edge and the tag of the function there.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct Crossing<S: RealField> {
+    pub plane: Plane<S>,
+    pub tag: Option<u32>,
+}
+
 /// Struct containing all the intermediary state for the different stages of tessellation.
 #[derive(Clone)]
 pub struct ManifoldDualContouring<'a, S: RealField> {
     function: &'a dyn ImplicitFunction<S>,
-    origin: na::Point3<S>,
-    dim: [usize; 3],
-    mesh: RefCell<Mesh<S>>,
-    res: S,
+    pub(crate) origin: na::Point3<S>,
+    pub(crate) dim: [usize; 3],
+    pub(crate) res: na::Vector3<S>,
     error: S,
-    value_grid: HashMap<Index, S>,
-    edge_grid: RefCell<HashMap<EdgeIndex, Plane<S>>>,
+    // Vertices are never merged beyond this octree layer.
+    max_layer: usize,
+    pub(crate) value_grid: SparseGrid<Index, S>,
+    pub(crate) edge_grid: RefCell<SparseGrid<EdgeIndex, Crossing<S>>>,
     // The Vertex Octtree. vertex_octtree[0] stores the leaf vertices. vertex_octtree[1] the next
     // layer and so on. vertex_octtree.len() is the depth of the octtree.
     vertex_octtree: Vec<Vec<Vertex<S>>>,
     // Map from VertexIndex to vertex_octtree[0]
-    vertex_index_map: HashMap<VertexIndex, usize>,
+    vertex_index_map: FastHashMap<VertexIndex, usize>,
+    options: TessellationOptions<S>,
+    // The block of the grid to tessellate in chunked mode. None tessellates the whole grid.
+    chunk: Option<Chunk>,
+    // In chunked mode: map from mesh index to the vertex index of all vertices shared with
+    // neighboring chunks.
+    shared_vertices: RefCell<HashMap<usize, VertexIndex>>,
+    // In periodic mode: number of cells per period. The grid wraps around after that many cells,
+    // starting at CHUNK_PADDING.
+    period: Option<Index>,
+    // Samples the attributes of each vertex added to the mesh.
+    attributes: Option<&'a dyn VertexAttributes<S>>,
+    // Pool for the parallel stages if options.threads is set. Built on first use and shared with
+    // the chunk tessellators.
+    thread_pool: Option<Arc<rayon::ThreadPool>>,
+    // In face budget mode: the rank of each octree vertex in order of increasing error, and the
+    // number of the cheapest vertices that may be merged. Empty otherwise, then error decides.
+    merge_rank: Vec<Vec<usize>>,
+    merge_count: usize,
+    // Whether the last tessellation met the face budget, if there was one.
+    face_budget_met: Option<bool>,
+}
+
+// Object safe version of the attribute callbacks, hiding the number of attributes.
+trait VertexAttributes<S: RealField> {
+    fn sample(&self, p: &na::Point3<S>) -> Vec<f32>;
+}
+
+impl<S: RealField, F: Fn(&na::Point3<S>) -> [f32; N], const N: usize> VertexAttributes<S> for F {
+    fn sample(&self, p: &na::Point3<S>) -> Vec<f32> {
+        self(p).to_vec()
+    }
+}
+
+// Number of grid points the grid is padded with in chunked mode, so that chunks at the border
+// can sample their halo.
+const CHUNK_PADDING: usize = 2;
+
+// A block of a larger grid that is tessellated on its own. All indexes are global.
+//
+// A chunk owns all edges starting at a grid point in [lo, hi). The quads of those edges use the
+// vertices of cells [lo - 1, hi - 1]. Vertices in the cells on that boundary are also used by
+// the neighboring chunks, so they are pinned: they are never simplified and thus identical in
+// both chunks. Building the octree neighbors of the boundary cells requires the cells
+// [lo - 2, hi], i.e. the grid points [lo - 2, hi + 2).
+#[derive(Clone, Copy, Debug)]
+struct Chunk {
+    lo: Index,
+    hi: Index,
+}
+
+impl Chunk {
+    // Returns true, if edges starting at idx belong to this chunk.
+    fn owns(&self, idx: Index) -> bool {
+        (0..3).all(|i| self.lo[i] <= idx[i] && idx[i] < self.hi[i])
+    }
+    // Returns true, if the sub-cube [idx, idx + size) overlaps the points sampled for this chunk.
+    fn overlaps(&self, idx: Index, size: usize) -> bool {
+        (0..3).all(|i| idx[i] + size + CHUNK_PADDING > self.lo[i] && idx[i] < self.hi[i] + CHUNK_PADDING)
+    }
+    // Returns true, if the grid point idx needs to be sampled for this chunk.
+    fn samples(&self, idx: Index) -> bool {
+        self.overlaps(idx, 1)
+    }
+    // Returns true, if the vertices of cell idx are shared with a neighboring chunk.
+    fn is_boundary_cell(&self, idx: Index) -> bool {
+        (0..3).any(|i| idx[i] + 1 == self.lo[i] || idx[i] + 1 == self.hi[i])
+    }
 }
 
 // Returns the next largest power of 2
@@ -229,6 +325,54 @@ fn pow2roundup(x: usize) -> usize {
     x + 1
 }
 
+// Returns the four cells sharing the minimal edge `edge_index`, each together with the edge
+// of that cell which coincides with `edge_index`. The cells are ordered around the edge.
+pub fn quad_cells(edge_index: EdgeIndex) -> [(Edge, Index); 4] {
+    debug_assert!((edge_index.edge as usize) < 4);
+    QUADS[edge_index.edge as usize]
+        .map(|quad_edge| (quad_edge, neg_offset(edge_index.index, EDGE_OFFSET[quad_edge as usize])))
+}
+
+// Add the quad spanned by the distinct points p to sink and return the number of triangles
+// added. Two triangles are generated for four points, one for three points and none for less.
+// If reverse is set, the winding is flipped - this is required for edges going from inside to
+// outside. If tag is set, the triangles are tagged with it.
+pub fn push_quad<S>(
+    sink: &mut dyn MeshSink<S>,
+    p: &mut [usize],
+    reverse: bool,
+    tag: Option<u32>,
+) -> usize {
+    if p.len() < 3 {
+        return 0;
+    }
+    if reverse {
+        p.reverse();
+    }
+    let mut add_triangle = |f| match tag {
+        Some(tag) => sink.add_tagged_triangle(f, tag),
+        None => sink.add_triangle(f),
+    };
+    // TODO: Fix this to choose the proper split.
+    add_triangle([p[0], p[1], p[2]]);
+    if p.len() == 4 {
+        add_triangle([p[2], p[3], p[0]]);
+        return 2;
+    }
+    1
+}
+
+// Add the faces of local to sink, mapping vertex indexes with global_index.
+fn add_faces<S>(sink: &mut dyn MeshSink<S>, local: &Mesh<S>, global_index: &[usize]) {
+    for (i, f) in local.faces.iter().enumerate() {
+        let f = f.map(|i| global_index[i]);
+        match local.face_tags.get(i) {
+            Some(&tag) => sink.add_tagged_triangle(f, tag),
+            None => sink.add_triangle(f),
+        }
+    }
+}
+
 // Returns a BitSet containing all egdes connected to "edge" in this cell.
 fn get_connected_edges(edge: Edge, cell: BitSet) -> BitSet {
     for &edge_set in CELL_CONFIGS[cell.as_u32() as usize].iter() {
@@ -294,11 +438,16 @@ fn add_child_to_parent<S: RealField + Float + From<f32>>(
     parent: &mut Vertex<S>,
 ) {
     parent.qef.borrow_mut().merge(&*child.qef.borrow());
+    add_child_neighbors(child, &mut parent.neighbors);
+}
+
+// Add the neighbors of child outside of its parent cell to neighbors.
+fn add_child_neighbors<S: RealField>(child: &Vertex<S>, neighbors: &mut [Vec<VarIndex>; 6]) {
     for dim in 0..3 {
         let relevant_neighbor = dim * 2 + (child.index[dim] & 1);
         for neighbor in &child.neighbors[relevant_neighbor] {
-            if !parent.neighbors[relevant_neighbor].contains(neighbor) {
-                parent.neighbors[relevant_neighbor].push(*neighbor);
+            if !neighbors[relevant_neighbor].contains(neighbor) {
+                neighbors[relevant_neighbor].push(*neighbor);
             }
         }
     }
@@ -334,14 +483,29 @@ fn subsample_euler_characteristics<S: RealField>(
     (intersections, euler)
 }
 
-fn subsample_octtree<S: RealField + Float + From<f32>>(base: &[Vertex<S>]) -> Vec<Vertex<S>> {
+// Group the vertices of base with the given indexes into the vertices of the next layer. All
+// vertices in the parent cells of members must be members. The new vertices get indexes starting
+// at offset.
+fn subsample_octtree<S: RealField + Float + From<f32>>(
+    base: &[Vertex<S>],
+    members: impl IntoIterator<Item = usize>,
+    offset: usize,
+) -> Vec<Vertex<S>> {
     let mut result = Vec::new();
-    for (i, vertex) in base.iter().enumerate() {
+    for i in members {
+        let vertex = &base[i];
         if vertex.parent.get().is_none() {
             let mut neighbor_set = BTreeSet::new();
             neighbor_set.insert(i);
             add_connected_vertices_in_subcell(base, vertex, &mut neighbor_set);
-            let (intersections, euler) = subsample_euler_characteristics(&neighbor_set, base);
+            let pinned = neighbor_set.iter().any(|&i| base[i].pinned);
+            // Pinned groups might miss vertices outside of their chunk. Since they are never used,
+            // there is no need for their euler characteristic.
+            let (intersections, euler) = if pinned {
+                ([0u32; 12], 0)
+            } else {
+                subsample_euler_characteristics(&neighbor_set, base)
+            };
             let mut parent = Vertex {
                 index: half_index(&vertex.index),
                 qef: RefCell::new(qef::Qef::new(&[], BoundingBox::neg_infinity())),
@@ -358,6 +522,8 @@ fn subsample_octtree<S: RealField + Float + From<f32>>(base: &[Vertex<S>]) -> Ve
                 mesh_index: Cell::new(None),
                 edge_intersections: intersections,
                 euler_characteristic: euler,
+                pinned,
+                removed: false,
             };
             for &neighbor_index in &neighbor_set {
                 let child = &base[neighbor_index];
@@ -370,30 +536,47 @@ fn subsample_octtree<S: RealField + Float + From<f32>>(base: &[Vertex<S>]) -> Ve
                 debug_assert!(!parent.children.contains(&neighbor_index));
                 parent.children.push(neighbor_index);
                 add_child_to_parent(child, &mut parent);
-                child.parent.set(Some(result.len()));
+                child.parent.set(Some(offset + result.len()));
             }
             result.push(parent);
         }
     }
     for vertex in &mut result {
-        for neighbor_vec in &mut vertex.neighbors {
-            for neighbor in neighbor_vec.iter_mut() {
-                match *neighbor {
-                    VarIndex::VertexIndex(_) => panic!("unexpected VertexIndex in normal node."),
-                    VarIndex::Index(i) => {
-                        *neighbor = VarIndex::Index(base[i].parent.get().unwrap())
-                    }
-                }
-            }
-        }
+        map_to_parents(base, &mut vertex.neighbors);
     }
     result
 }
 
+// Replace the neighbors in base by their parents.
+fn map_to_parents<S: RealField>(base: &[Vertex<S>], neighbors: &mut [Vec<VarIndex>; 6]) {
+    for neighbor in neighbors.iter_mut().flatten() {
+        match *neighbor {
+            VarIndex::VertexIndex(_) => panic!("unexpected VertexIndex in normal node."),
+            VarIndex::Index(i) => *neighbor = VarIndex::Index(base[i].parent.get().unwrap()),
+        }
+    }
+}
+
 // ---------------------------------------------------------------------------
 // Bounding-box discovery
 // ---------------------------------------------------------------------------
 
+// Divides the values of f by its Lipschitz constant, so that they never exceed the distance to the
+// surface. All functions below rely on that.
+struct DistanceBound<'a, S> {
+    f: &'a dyn ImplicitFunction<S>,
+    lipschitz: S,
+}
+
+impl<S: RealField + std::fmt::Debug> ImplicitFunction<S> for DistanceBound<'_, S> {
+    fn value(&self, p: &na::Point3<S>) -> S {
+        self.f.value(p) / self.lipschitz
+    }
+    fn normal(&self, p: &na::Point3<S>) -> na::Vector3<S> {
+        self.f.normal(p)
+    }
+}
+
 // Follow the implicit function's gradient downhill from a perturbed origin to find
 // a point that is inside (f < 0) or at the nearest surface point, then step inside.
 // The slight initial perturbation avoids degenerate normals at exact symmetry axes
@@ -434,8 +617,8 @@ fn march_axis<S: Float + RealField + From<f32>>(
     start: na::Point3<S>,
     dir: na::Vector3<S>,
     res: S,
+    max_dist: S,
 ) -> Option<na::Point3<S>> {
-    let max_dist: S = From::from(1000.0f32);
     let mut p = start;
     let mut v = f.value(&p);
     let init_sign = Float::signum(v);
@@ -583,16 +766,17 @@ fn face_corners<S: Float + RealField + From<f32>>(
 // does not exit through it.
 //
 // Each expansion shifts the face by at least `res`, so the loop terminates in at most
-// O(surface_extent / res) iterations per face.  The limit of 10_000 is a defensive
+// O(surface_extent / res) iterations per face.  The limit of max_steps is a defensive
 // safety-cap for degenerate functions that somehow evade the SDF guarantee.
 fn verify_and_expand<S: Float + RealField + From<f32>>(
     f: &dyn ImplicitFunction<S>,
     res: S,
+    max_steps: usize,
     bbox: &mut BoundingBox<S, 3>,
 ) {
     for axis in 0..3usize {
         for &is_max in &[false, true] {
-            for _ in 0..10_000 {
+            for _ in 0..max_steps {
                 let corners = face_corners(bbox, axis, is_max);
                 let v = [
                     f.value(&corners[0]),
@@ -628,8 +812,14 @@ fn verify_and_expand<S: Float + RealField + From<f32>>(
 // expanding each face until the surface is fully enclosed.
 fn find_bounds<S: Float + RealField + From<f32>>(
     f: &dyn ImplicitFunction<S>,
-    res: S,
+    options: &TessellationOptions<S>,
 ) -> BoundingBox<S, 3> {
+    let bound = DistanceBound {
+        f,
+        lipschitz: f.lipschitz(),
+    };
+    let f: &dyn ImplicitFunction<S> = &bound;
+    let res = options.min_res();
     let hint = find_hint(f, res);
 
     let zero: S = From::from(0f32);
@@ -645,7 +835,7 @@ fn find_bounds<S: Float + RealField + From<f32>>(
 
     let mut surface_points: Vec<na::Point3<S>> = Vec::new();
     for dir in dirs {
-        if let Some(p) = march_axis(f, hint, dir, res) {
+        if let Some(p) = march_axis(f, hint, dir, res, options.max_march_distance) {
             surface_points.push(p);
         }
     }
@@ -686,10 +876,207 @@ fn find_bounds<S: Float + RealField + From<f32>>(
 
     let pad = na::Vector3::new(res, res, res);
     let mut bbox = BoundingBox::new(&(min_pt - pad), &(max_pt + pad));
-    verify_and_expand(f, res, &mut bbox);
+    verify_and_expand(f, res, options.max_expand_steps, &mut bbox);
     bbox
 }
 
+// Box of octree cells, including lo and hi.
+struct CellBox {
+    lo: Index,
+    hi: Index,
+}
+
+impl CellBox {
+    fn contains(&self, idx: Index) -> bool {
+        (0..3).all(|i| self.lo[i] <= idx[i] && idx[i] <= self.hi[i])
+    }
+    // Returns whether idx is outside of self, but touches it.
+    fn is_next_to(&self, idx: Index) -> bool {
+        !self.contains(idx) && (0..3).all(|i| self.lo[i] <= idx[i] + 1 && idx[i] <= self.hi[i] + 1)
+    }
+    fn cells(&self) -> impl Iterator<Item = Index> + '_ {
+        (self.lo[2]..=self.hi[2]).flat_map(move |z| {
+            (self.lo[1]..=self.hi[1])
+                .flat_map(move |y| (self.lo[0]..=self.hi[0]).map(move |x| [x, y, z]))
+        })
+    }
+}
+
+// Returns the minimal edges, whose quads use the leaf vertex_index.
+fn minimal_edges(vertex_index: &VertexIndex) -> impl Iterator<Item = EdgeIndex> + '_ {
+    vertex_index.edges.into_iter().map(|edge| {
+        EdgeIndex {
+            edge: Edge::from_usize(edge),
+            index: vertex_index.index,
+        }
+        .base()
+    })
+}
+
+/// Error of [`ManifoldDualContouring::retessellate_region`].
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum RetessellateError {
+    /// There is no previous tessellation of the whole grid to update. Chunked and periodic
+    /// tessellations can not be updated.
+    NotTessellated,
+}
+
+impl fmt::Display for RetessellateError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RetessellateError::NotTessellated => write!(f, "nothing was tessellated before"),
+        }
+    }
+}
+
+impl std::error::Error for RetessellateError {}
+
+// MeshSink that adds vertices and faces to an existing mesh. Vertices at the position of one of
+// the removed vertices take its slot. Other new vertices are appended and moved into the
+// remaining slots by finish.
+struct MeshPatch<'m, S> {
+    mesh: &'m mut Mesh<S>,
+    free_slots: HashMap<[(u64, i16, i8); 3], Vec<usize>>,
+    is_free: Vec<bool>,
+}
+
+impl<'m, S: Float> MeshPatch<'m, S> {
+    fn new(mesh: &'m mut Mesh<S>, removed_vertices: &HashSet<usize>) -> MeshPatch<'m, S> {
+        let mut free_slots: HashMap<_, Vec<usize>> = HashMap::new();
+        let mut is_free = vec![false; mesh.vertices.len()];
+        let mut removed: Vec<usize> = removed_vertices.iter().copied().collect();
+        removed.sort_unstable_by(|a, b| b.cmp(a));
+        for i in removed {
+            free_slots.entry(Self::key(&mesh.vertices[i])).or_default().push(i);
+            is_free[i] = true;
+        }
+        MeshPatch {
+            mesh,
+            free_slots,
+            is_free,
+        }
+    }
+    fn key(v: &[S; 3]) -> [(u64, i16, i8); 3] {
+        v.map(|c| c.integer_decode())
+    }
+    fn take_slot(&mut self, v: [S; 3]) -> Option<usize> {
+        let slot = self.free_slots.get_mut(&Self::key(&v))?.pop()?;
+        self.is_free[slot] = false;
+        self.mesh.vertices[slot] = v;
+        Some(slot)
+    }
+    // Fill the remaining free slots with the last vertices and return where they moved.
+    fn finish(self) -> HashMap<usize, usize> {
+        let mut moved = HashMap::new();
+        let is_free = |i: usize| self.is_free.get(i) == Some(&true);
+        let mut free = (0..self.is_free.len()).filter(|&i| is_free(i));
+        let mut len = self.mesh.vertices.len();
+        loop {
+            // Free slots at the end are simply dropped.
+            while len > 0 && is_free(len - 1) {
+                len -= 1;
+            }
+            let Some(slot) = free.next().filter(|&slot| slot < len) else {
+                break;
+            };
+            len -= 1;
+            self.mesh.vertices.swap(slot, len);
+            let stride = self.mesh.attribute_stride;
+            for k in 0..stride {
+                self.mesh.vertex_attributes.swap(slot * stride + k, len * stride + k);
+            }
+            moved.insert(len, slot);
+        }
+        self.mesh.vertices.truncate(len);
+        self.mesh.vertex_attributes.truncate(len * self.mesh.attribute_stride);
+        for f in &mut self.mesh.faces {
+            for v in f.iter_mut() {
+                if let Some(&i) = moved.get(v) {
+                    *v = i;
+                }
+            }
+        }
+        moved
+    }
+}
+
+impl<S: Float> MeshSink<S> for MeshPatch<'_, S> {
+    fn add_vertex(&mut self, v: [S; 3]) -> usize {
+        match self.take_slot(v) {
+            Some(slot) => {
+                self.mesh.attributes_mut(slot).fill(0.);
+                slot
+            }
+            None => self.mesh.add_vertex(v),
+        }
+    }
+    fn add_vertex_with_attributes(&mut self, v: [S; 3], attributes: &[f32]) -> usize {
+        match self.take_slot(v) {
+            Some(slot) => {
+                if self.mesh.attribute_stride > 0 {
+                    self.mesh.attributes_mut(slot).copy_from_slice(attributes);
+                }
+                slot
+            }
+            None => self.mesh.add_vertex_with_attributes(v, attributes),
+        }
+    }
+    fn add_triangle(&mut self, f: [usize; 3]) {
+        self.mesh.add_triangle(f);
+    }
+    fn add_tagged_triangle(&mut self, f: [usize; 3], tag: u32) {
+        self.mesh.add_tagged_triangle(f, tag);
+    }
+}
+
+// MeshSink that records the faces of vertices, which are in the mesh already.
+#[derive(Default)]
+struct FaceRecorder {
+    faces: Vec<[usize; 3]>,
+}
+
+impl<S> MeshSink<S> for FaceRecorder {
+    fn add_vertex(&mut self, _: [S; 3]) -> usize {
+        unreachable!("all vertices of the recorded faces exist already")
+    }
+    fn add_triangle(&mut self, f: [usize; 3]) {
+        self.faces.push(f);
+    }
+}
+
+// Rotate the vertices of f, so that the smallest one comes first.
+fn rotate_to_min(f: [usize; 3]) -> [usize; 3] {
+    let i = (0..3).min_by_key(|&i| f[i]).unwrap();
+    [f[i], f[(i + 1) % 3], f[(i + 2) % 3]]
+}
+
+// MeshSink that only counts vertices and faces.
+#[derive(Default)]
+struct FaceCounter {
+    vertices: usize,
+    faces: usize,
+}
+
+impl<S> MeshSink<S> for FaceCounter {
+    fn add_vertex(&mut self, _: [S; 3]) -> usize {
+        self.vertices += 1;
+        self.vertices - 1
+    }
+    fn add_triangle(&mut self, _: [usize; 3]) {
+        self.faces += 1;
+    }
+}
+
+/// Level of detail for [`ManifoldDualContouring::tessellate_lods`].
+#[derive(Clone, Copy, Debug)]
+pub enum LevelOfDetail<S> {
+    /// Merge vertices as long as the error stays below the threshold, relative to `res`.
+    RelativeError(S),
+    /// Merge vertices up to the given number of octree layers above the leafs, regardless of the
+    /// error. Depth 0 keeps the leaf vertices.
+    Depth(usize),
+}
+
 /// Progress event emitted by [`ManifoldDualContouring::tessellate_with_progress`].
 #[derive(Clone, Debug)]
 #[allow(missing_docs)] // field names (done, total, layer, face_count) are self-documenting
@@ -742,235 +1129,1081 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
     /// f: function to tessellate
     /// res: resolution
     /// relative_error: acceptable error threshold when simplifying the mesh.
+    ///
+    /// This is a shorthand for [`with_options`](Self::with_options) with default options.
     pub fn new(
         f: &'a dyn ImplicitFunction<S>,
         res: S,
         relative_error: S,
+    ) -> ManifoldDualContouring<'a, S> {
+        Self::with_options(f, TessellationOptions::new(res).relative_error(relative_error))
+    }
+    /// Constructor
+    /// f: function to tessellate
+    /// options: resolution and all other settings
+    pub fn with_options(
+        f: &'a dyn ImplicitFunction<S>,
+        options: TessellationOptions<S>,
     ) -> ManifoldDualContouring<'a, S> {
         ManifoldDualContouring {
             function: f,
             origin: na::Point3::origin(),
             dim: [0, 0, 0],
-            mesh: RefCell::new(Mesh {
-                vertices: Vec::new(),
-                faces: Vec::new(),
-            }),
-            res,
-            error: res * relative_error,
-            value_grid: HashMap::new(),
-            edge_grid: RefCell::new(HashMap::new()),
+            res: options.res,
+            error: options.min_res() * options.relative_error,
+            max_layer: usize::MAX,
+            value_grid: SparseGrid::new(),
+            edge_grid: RefCell::new(SparseGrid::new()),
             vertex_octtree: Vec::new(),
-            vertex_index_map: HashMap::new(),
+            vertex_index_map: FastHashMap::default(),
+            options,
+            chunk: None,
+            shared_vertices: RefCell::new(HashMap::new()),
+            period: None,
+            attributes: None,
+            thread_pool: None,
+            merge_rank: Vec::new(),
+            merge_count: 0,
+            face_budget_met: None,
         }
     }
+    /// Enable adaptive sampling.
+    /// See [`TessellationOptions::adaptive_sampling`] for details.
+    pub fn with_adaptive_sampling(mut self, max_normal_angle: S, relative_error: S) -> Self {
+        self.options = self.options.adaptive_sampling(max_normal_angle, relative_error);
+        self
+    }
+    /// Sample `attributes` at the position of each vertex of the mesh, e.g. to get colours or
+    /// material densities from a field accompanying the function.
+    ///
+    /// The attributes are passed to
+    /// [`MeshSink::add_vertex_with_attributes`] and stored in
+    /// [`Mesh::vertex_attributes`], see [`Mesh::attributes`]. [`PlyWriter`](crate::PlyWriter)
+    /// writes them as vertex properties.
+    pub fn with_vertex_attributes<const N: usize>(
+        mut self,
+        attributes: &'a impl Fn(&na::Point3<S>) -> [f32; N],
+    ) -> Self {
+        self.attributes = Some(attributes);
+        self
+    }
     /// Tessellate the given function.
+    ///
+    /// The result is deterministic: identical inputs yield identical vertices and faces in
+    /// identical order.
     pub fn tessellate(&mut self) -> Option<Mesh<S>> {
         self.tessellate_with_progress(|_| {})
     }
 
     /// Tessellate, calling `progress` after each pipeline stage with a [`ProgressEvent`].
     /// The callback is called from the same thread and may update UI or send messages.
-    pub fn tessellate_with_progress(&mut self, mut progress: impl FnMut(ProgressEvent)) -> Option<Mesh<S>> {
-        let one: S = From::from(1f32);
-        let mut bbox = find_bounds(self.function, self.res);
-        bbox.dilate(one + self.res * From::from(1.1f32));
-        self.origin = bbox.min;
-        self.dim = std::array::from_fn(|i| Float::ceil(bbox.dim()[i] / self.res).as_usize());
+    pub fn tessellate_with_progress(&mut self, progress: impl FnMut(ProgressEvent)) -> Option<Mesh<S>> {
+        let mut mesh = Mesh::default();
+        self.tessellate_into(&mut mesh, progress);
+        Some(mesh)
+    }
+
+    /// Tessellate and pass all vertices and triangles to `sink` as soon as they are generated,
+    /// instead of collecting them in a [`Mesh`]. Otherwise identical to
+    /// [`tessellate_with_progress`](Self::tessellate_with_progress).
+    pub fn tessellate_into(
+        &mut self,
+        sink: &mut dyn MeshSink<S>,
+        mut progress: impl FnMut(ProgressEvent),
+    ) {
+        self.find_grid();
         progress(ProgressEvent::BoundsFound);
-        loop {
-            match self.try_tessellate(&mut progress) {
-                Ok(mesh) => return Some(mesh),
-                // Tessellation failed because a grid cell value was exactly zero.
-                // Retry with random padding and hope for the best.
-                Err(_) => {
-                    let padding = na::Vector3::new(
-                        -self.res / From::from(10. + rand::random::<f32>().abs()),
-                        -self.res / From::from(10. + rand::random::<f32>().abs()),
-                        -self.res / From::from(10. + rand::random::<f32>().abs()),
-                    );
-                    self.origin += padding;
-                    self.value_grid.clear();
-                    self.mesh.borrow_mut().vertices.clear();
-                    self.mesh.borrow_mut().faces.clear();
-                    self.vertex_octtree.clear();
-                    self.vertex_index_map.clear();
+        self.tessellate_grid(sink, &mut progress);
+    }
+
+    /// Returns whether the last tessellation had at most
+    /// [`face_budget`](TessellationOptions::face_budget) faces, or `None`, if no budget was
+    /// applied.
+    pub fn face_budget_met(&self) -> Option<bool> {
+        self.face_budget_met
+    }
+
+    /// Tessellate the given function once and extract a mesh for each level of detail in `lods`.
+    ///
+    /// Sampling, edge crossings and the octree are computed only once and shared by all levels,
+    /// which makes this a lot cheaper than tessellating the function for each level. The
+    /// `relative_error` of self is ignored.
+    pub fn tessellate_lods(&mut self, lods: &[LevelOfDetail<S>]) -> Vec<Mesh<S>> {
+        let mut progress = |_| {};
+        self.find_grid();
+        self.sample_surface(&mut progress);
+        self.build_octree(&mut progress);
+        lods.iter()
+            .map(|lod| {
+                (self.error, self.max_layer) = match *lod {
+                    LevelOfDetail::RelativeError(e) => (self.options.min_res() * e, usize::MAX),
+                    LevelOfDetail::Depth(depth) => (Float::infinity(), depth),
+                };
+                for vertex in self.vertex_octtree.iter().flatten() {
+                    vertex.mesh_index.set(None);
                 }
-            }
+                let mut mesh = Mesh::default();
+                self.generate_quads(&mut mesh, &mut progress);
+                mesh
+            })
+            .collect()
+    }
+
+    /// Update `mesh` after the function changed inside `bbox` only.
+    ///
+    /// `f` is the changed function and `mesh` must be the result of the last call to
+    /// [`tessellate`](Self::tessellate) or [`tessellate_into`](Self::tessellate_into) on self.
+    /// Only the grid points within `bbox` are sampled again, and only the octree vertices above
+    /// them are rebuilt. The faces, whose vertices might change by this, are replaced, all others
+    /// are kept. Vertices of `mesh` that did not move keep their index. The error threshold of
+    /// the last tessellation is kept, so a face budget is not applied again. Parts of the surface
+    /// that moved outside of the grid of the first tessellation are lost.
+    ///
+    /// Fails, if self has not tessellated the whole grid before, e.g. only in chunks.
+    pub fn retessellate_region(
+        &mut self,
+        f: &'a dyn ImplicitFunction<S>,
+        bbox: &BoundingBox<S, 3>,
+        mesh: &mut Mesh<S>,
+    ) -> Result<(), RetessellateError> {
+        if self.chunk.is_some() || self.vertex_octtree.is_empty() {
+            return Err(RetessellateError::NotTessellated);
         }
-    }
-
-    fn build_value_grid(&mut self, progress: &mut impl FnMut(ProgressEvent)) -> Option<DualContouringError> {
-        let maxdim = cmp::max(self.dim[0], cmp::max(self.dim[1], self.dim[2]));
-        let origin = self.origin;
-        let origin_value = self.function.value(&origin);
-        let total = self.dim[0] * self.dim[1] * self.dim[2];
-        let mut done = 0usize;
-        self.sample_value_grid([0, 0, 0], origin, pow2roundup(maxdim), origin_value, &mut done, total, progress)
-    }
+        // The ranks do not cover rebuilt vertices, merge by the error threshold instead.
+        self.merge_rank.clear();
+        self.function = f;
+        // All edges touching bbox, including the ones leaving it, start and end in lo..=hi.
+        let lo: Index = std::array::from_fn(|i| {
+            let steps = Float::floor((bbox.min[i] - self.origin[i]) / self.res[i]);
+            Float::max(steps, S::zero()).as_usize().saturating_sub(1)
+        });
+        let hi: Index = std::array::from_fn(|i| {
+            let steps = Float::ceil((bbox.max[i] - self.origin[i]) / self.res[i]);
+            cmp::min(Float::max(steps, S::zero()).as_usize() + 1, self.dim[i])
+        });
+        // Octree vertices in these cells of each layer are rebuilt. Leafs change in the cells
+        // lo..hi, the leafs next to them refer to them as neighbors.
+        let rebuilt: Vec<CellBox> = (0..self.vertex_octtree.len())
+            .map(|layer| CellBox {
+                lo: lo.map(|i| i.saturating_sub(1) >> layer),
+                hi: hi.map(|i| (i + 1) >> layer),
+            })
+            .collect();
 
-    // This method does the main work of tessellation.
-    // It may fail, if the value in one of the grid cells yields exactly zero.
-    fn try_tessellate(&mut self, progress: &mut impl FnMut(ProgressEvent)) -> Result<Mesh<S>, DualContouringError> {
-        if let Some(e) = self.build_value_grid(progress) {
-            return Err(e);
+        let removed = self.vertex_octtree.iter().flatten().filter(|v| v.removed).count();
+        if 2 * removed > self.vertex_octtree.iter().map(Vec::len).sum() {
+            // Too many removed vertices, start over.
+            self.sample_region(lo, hi);
+            self.vertex_octtree.clear();
+            let mut progress = |_| {};
+            self.build_octree(&mut progress);
+            self.solve_qefs(&mut progress);
+            let mut updated = Mesh::default();
+            self.generate_quads(&mut updated, &mut progress);
+            mesh.replace_reusing_indices(updated);
+            return Ok(());
         }
 
-        let compact_total = self.value_grid.len();
-        progress(ProgressEvent::CompactingGrid { done: 0, total: compact_total });
-        self.compact_value_grid();
-        progress(ProgressEvent::CompactingGrid { done: compact_total, total: compact_total });
-
-        self.generate_edge_grid(progress);
-
-        let (leafs, index_map) = self.generate_leaf_vertices(progress);
-        self.vertex_index_map = index_map;
-        self.vertex_octtree.push(leafs);
-
-        let mut layer = 0usize;
-        loop {
-            progress(ProgressEvent::OctreeLayer { layer });
-            let next = subsample_octtree(self.vertex_octtree.last().unwrap());
-            if next.len() == self.vertex_octtree.last().unwrap().len() {
-                break;
+        // A leaf is represented in mesh by the first vertex with a mesh index above it. If that
+        // vertex or its parent is rebuilt, the leaf might be represented by another vertex
+        // afterwards, so the faces of all its edges are replaced.
+        let top = self.vertex_octtree.len() - 1;
+        let mut invalid = Vec::new();
+        let mut quad_edges = Vec::new();
+        let mut removed_vertices = HashSet::new();
+        for (vertex_index, &leaf) in &self.vertex_index_map {
+            if !rebuilt[top].contains(vertex_index.index.map(|i| i >> top)) {
+                continue;
+            }
+            let (mut layer, mut i) = (0, leaf);
+            let represented = loop {
+                let vertex = &self.vertex_octtree[layer][i];
+                if let Some(mesh_index) = vertex.mesh_index.get() {
+                    break Some((layer, i, mesh_index));
+                }
+                match vertex.parent.get() {
+                    Some(parent) => (layer, i) = (layer + 1, parent),
+                    None => break None,
+                }
+            };
+            let Some((layer, i, mesh_index)) = represented else {
+                continue;
+            };
+            let vertex = &self.vertex_octtree[layer][i];
+            if rebuilt[layer].contains(vertex.index)
+                || (layer < top && rebuilt[layer + 1].contains(half_index(&vertex.index)))
+            {
+                invalid.push((layer, i));
+                removed_vertices.insert(mesh_index);
+                quad_edges.extend(minimal_edges(vertex_index));
             }
-            self.vertex_octtree.push(next);
-            layer += 1;
         }
-
-        self.solve_qefs(progress);
-
-        let total_quads = self.edge_grid.borrow().len();
-        for (i, &edge_index) in self.edge_grid.borrow().keys().enumerate() {
-            progress(ProgressEvent::GeneratingQuad { done: i + 1, total: total_quads });
-            self.compute_quad(edge_index);
+        quad_edges.sort_by_key(|e| (e.index, e.edge as usize));
+        quad_edges.dedup();
+        // Find the current faces of these edges. All their vertices are in mesh already.
+        let mut old_faces = FaceRecorder::default();
+        for &edge_index in &quad_edges {
+            self.compute_quad(edge_index, &mut old_faces);
+        }
+        for (layer, i) in invalid {
+            self.vertex_octtree[layer][i].mesh_index.set(None);
         }
 
-        let face_count = self.mesh.borrow().faces.len();
-        progress(ProgressEvent::Done { face_count });
+        self.sample_region(lo, hi);
+        let new_leafs = self.rebuild_leafs(&rebuilt[0]);
+        for (layer, rebuilt) in rebuilt.iter().enumerate().skip(1) {
+            self.rebuild_layer(layer, rebuilt);
+        }
 
-        Ok(self.mesh.borrow().clone())
+        // Replace the faces of all edges of changed leafs.
+        let mut old_faces = old_faces.faces.into_iter().fold(HashMap::new(), |mut m, f| {
+            *m.entry(rotate_to_min(f)).or_insert(0) += 1;
+            m
+        });
+        let removed_faces: Vec<bool> = mesh
+            .faces
+            .iter()
+            .map(|&f| match old_faces.get_mut(&rotate_to_min(f)) {
+                Some(count) if *count > 0 => {
+                    *count -= 1;
+                    true
+                }
+                _ => false,
+            })
+            .collect();
+        let mut removed_face = removed_faces.iter();
+        mesh.faces.retain(|_| !removed_face.next().unwrap());
+        let mut removed_face = removed_faces.iter();
+        mesh.face_tags.retain(|_| !removed_face.next().unwrap());
+        debug_assert!(mesh.faces.iter().flatten().all(|v| !removed_vertices.contains(v)));
+        quad_edges.extend(new_leafs.iter().flat_map(minimal_edges));
+        quad_edges.sort_by_key(|e| (e.index, e.edge as usize));
+        quad_edges.dedup();
+        let mut patch = MeshPatch::new(mesh, &removed_vertices);
+        for edge_index in quad_edges {
+            if self.edge_grid.borrow().get(&edge_index).is_some() {
+                self.compute_quad(edge_index, &mut patch);
+            }
+        }
+        let moved = patch.finish();
+        if !moved.is_empty() {
+            for vertex in self.vertex_octtree.iter().flatten() {
+                if let Some(&i) = vertex.mesh_index.get().and_then(|i| moved.get(&i)) {
+                    vertex.mesh_index.set(Some(i));
+                }
+            }
+        }
+        Ok(())
     }
 
-    #[allow(clippy::too_many_arguments)]
-    fn sample_value_grid(
-        &mut self,
-        idx: Index,
-        pos: na::Point3<S>,
-        size: usize,
-        val: S,
-        done: &mut usize,
-        total: usize,
-        progress: &mut impl FnMut(ProgressEvent),
-    ) -> Option<DualContouringError> {
-        debug_assert!(size > 1);
-        let mut midx = idx;
-        let size = size / 2;
-        let size_s: S = From::from(size as f32);
-        let vpos = [
-            pos,
-            pos + na::Vector3::new(self.res, self.res, self.res) * size_s,
-        ];
-        let sub_cube_diagonal = size_s * self.res * Float::sqrt(From::from(3f32));
-
-        for z in 0..2 {
-            for y in 0..2 {
-                for x in 0..2 {
-                    let mpos = na::Point3::new(vpos[x].x, vpos[y].y, vpos[z].z);
-                    let value = if midx == idx {
-                        val
-                    } else {
-                        self.function.value(&mpos)
-                    };
+    // Sample the grid points lo..=hi again and find the crossings of all edges starting there.
+    fn sample_region(&mut self, lo: Index, hi: Index) {
+        let region = CellBox { lo, hi };
+        // The region is not compacted again, so its values are all kept.
+        for idx in region.cells() {
+            let value = self.with_function(|f| f.value(&self.grid_point(idx)));
+            self.value_grid.insert(idx, value);
+        }
+        let mut edge_grid = self.edge_grid.borrow_mut();
+        edge_grid.retain(|edge, _| !region.contains(edge.index));
+        for idx in region.cells() {
+            self.insert_edges(&mut edge_grid, idx, self.value_grid[&idx]);
+        }
+    }
 
-                    if value == From::from(0f32) {
-                        return Some(DualContouringError::HitZero(format!("{}", mpos)));
-                    }
+    // Replace the leafs in the rebuilt cells by new ones generated from the edge grid and return
+    // their vertex indexes. The leafs next to them get the new ones as neighbors.
+    fn rebuild_leafs(&mut self, rebuilt: &CellBox) -> Vec<VertexIndex> {
+        let mut replaced = HashMap::new();
+        self.vertex_index_map.retain(|vertex_index, &mut i| {
+            let keep = !rebuilt.contains(vertex_index.index);
+            if !keep {
+                replaced.insert(i, *vertex_index);
+            }
+            keep
+        });
+        for &i in replaced.keys() {
+            self.vertex_octtree[0][i].removed = true;
+        }
 
-                    if size > 1 && Float::abs(value) <= sub_cube_diagonal {
-                        if let Some(e) = self.sample_value_grid(midx, mpos, size, value, done, total, progress) {
-                            return Some(e);
-                        }
-                    } else {
-                        self.value_grid.insert(midx, value);
-                        *done += 1;
-                        progress(ProgressEvent::SamplingGrid { done: *done, total });
-                    }
-                    midx[0] += size;
+        // The edges of the rebuilt cells start in them or one above. This also generates leafs
+        // outside of the rebuilt cells, which are dropped.
+        let mut vertices = Vec::new();
+        let mut index_map = FastHashMap::default();
+        let edge_starts = CellBox {
+            lo: rebuilt.lo,
+            hi: rebuilt.hi.map(|i| i + 1),
+        };
+        for index in edge_starts.cells() {
+            for edge in [Edge::A, Edge::B, Edge::C] {
+                let edge_index = EdgeIndex { edge, index };
+                if self.edge_grid.borrow().get(&edge_index).is_some() {
+                    self.add_vertices_for_minimal_egde(&edge_index, &mut vertices, &mut index_map);
                 }
-                midx[0] -= 2 * size;
-                midx[1] += size;
             }
-            midx[1] -= 2 * size;
-            midx[2] += size;
         }
-        None
-    }
-
-    // Delete all values from value grid that do not have a value of opposing signum in any
-    // neighboring index.
-    // This might reduces memory usage by ~10x.
-    fn compact_value_grid(&mut self) {
-        // Collect all indexes to remove.
-        let value_grid = &mut self.value_grid;
-        let keys_to_remove: Vec<_> = value_grid
-            .par_iter()
-            .filter(|&(idx, &v)| {
-                if idx[0] == 0 || idx[1] == 0 || idx[2] == 0 {
-                    // This grid cell does not have neighbors in some directions. Ignore.
-                    return false;
+        let mut vertex_indexes = vec![None; vertices.len()];
+        for (vertex_index, &i) in &index_map {
+            vertex_indexes[i] = Some(*vertex_index);
+        }
+        let leafs = &mut self.vertex_octtree[0];
+        let first = leafs.len();
+        let mut new_leafs = Vec::new();
+        for (vertex, vertex_index) in vertices.into_iter().zip(vertex_indexes) {
+            let vertex_index = vertex_index.unwrap();
+            if rebuilt.contains(vertex_index.index) {
+                self.vertex_index_map.insert(vertex_index, leafs.len());
+                new_leafs.push(vertex_index);
+                leafs.push(vertex);
+            }
+        }
+        for vertex in &mut leafs[first..] {
+            for neighbor in vertex.neighbors.iter_mut().flatten() {
+                if let VarIndex::VertexIndex(vi) = *neighbor {
+                    *neighbor = VarIndex::Index(self.vertex_index_map[&vi]);
                 }
-                for z in 0..3 {
-                    for y in 0..3 {
-                        for x in 0..3 {
-                            let adjacent_idx = [idx[0] + x - 1, idx[1] + y - 1, idx[2] + z - 1];
-                            if let Some(&adjacent_value) = value_grid.get(&adjacent_idx)
-                                && Float::signum(v) != Float::signum(adjacent_value)
-                            {
-                                // Don't collect indexes with opposing signum.
-                                return false;
-                            }
-                        }
-                    }
+            }
+        }
+        // The cells next to the rebuilt ones did not change, so their neighbors still exist.
+        for vertex in &mut leafs[..first] {
+            if vertex.removed || !rebuilt.is_next_to(vertex.index) {
+                continue;
+            }
+            for neighbor in vertex.neighbors.iter_mut().flatten() {
+                if let VarIndex::Index(i) = *neighbor
+                    && let Some(vertex_index) = replaced.get(&i)
+                {
+                    *neighbor = VarIndex::Index(self.vertex_index_map[vertex_index]);
                 }
-                true
-            })
-            .map(|(k, _)| *k)
-            .collect();
-        for k in keys_to_remove {
-            value_grid.remove(&k);
+            }
         }
-        value_grid.shrink_to_fit();
+        new_leafs
+    }
+
+    // Replace the vertices of layer in the rebuilt cells by regrouping all vertices of the layer
+    // below in them. The vertices next to them get the new ones as neighbors.
+    fn rebuild_layer(&mut self, layer: usize, rebuilt: &CellBox) {
+        let (below, above) = self.vertex_octtree.split_at_mut(layer);
+        let (base, vertices) = (&below[layer - 1], &mut above[0]);
+        let members: Vec<usize> = (0..base.len())
+            .filter(|&i| !base[i].removed && rebuilt.contains(half_index(&base[i].index)))
+            .collect();
+        for &i in &members {
+            base[i].parent.set(None);
+        }
+        for vertex in vertices.iter_mut() {
+            if rebuilt.contains(vertex.index) {
+                vertex.removed = true;
+            }
+        }
+        let first = vertices.len();
+        vertices.extend(subsample_octtree(base, members, first));
+        for vertex in &mut vertices[..first] {
+            if vertex.removed || !rebuilt.is_next_to(vertex.index) {
+                continue;
+            }
+            let mut neighbors = Default::default();
+            for &child in &vertex.children {
+                add_child_neighbors(&base[child], &mut neighbors);
+            }
+            map_to_parents(base, &mut neighbors);
+            vertex.neighbors = neighbors;
+        }
+    }
+
+    /// Tessellate the given function in chunks of `chunk_size`³ cells.
+    ///
+    /// Only the grids and the octree of a single chunk are kept in memory at any time, plus the
+    /// mesh indexes of the vertices on the boundaries of chunks that are not done yet. Each chunk
+    /// walks the sampling octree from its root and skips the sub-cubes outside of it. The result
+    /// is identical to [`tessellate`](Self::tessellate), except that the mesh is not simplified
+    /// across chunk boundaries.
+    pub fn tessellate_chunked(&mut self, chunk_size: usize) -> Option<Mesh<S>> {
+        let mut mesh = Mesh::default();
+        self.tessellate_chunked_into(chunk_size, &mut mesh);
+        Some(mesh)
+    }
+
+    /// Tessellate the given function in chunks of `chunk_size`³ cells and pass each chunk to
+    /// `sink` as soon as it is done.
+    ///
+    /// Vertices on chunk boundaries are added to `sink` only once and shared by the triangles of
+    /// all chunks, so the resulting mesh is watertight.
+    pub fn tessellate_chunked_into(&mut self, chunk_size: usize, sink: &mut dyn MeshSink<S>) {
+        assert!(chunk_size > 0, "chunk_size must be positive");
+        self.face_budget_met = None;
+        self.find_grid();
+        let padding: S = From::from(CHUNK_PADDING as f32);
+        self.origin -= self.res * padding;
+        let cells = self.dim;
+        self.set_dim(cells.map(|d| d + 2 * CHUNK_PADDING));
+        let num_chunks = cells.map(|d| d.div_ceil(chunk_size));
+        // Build the pool here, so that all chunks share it.
+        self.thread_pool();
+
+        // Indexes in sink of the vertices on boundaries of chunks that are not done yet.
+        let mut shared = HashMap::new();
+        // Linear index of the last chunk that uses the vertices of the cell of key.
+        let last_chunk = |key: &VertexIndex| {
+            let [x, y, z]: Index = std::array::from_fn(|i| {
+                cmp::min((key.index[i] + 1 - CHUNK_PADDING) / chunk_size, num_chunks[i] - 1)
+            });
+            (z * num_chunks[1] + y) * num_chunks[0] + x
+        };
+        // The keys to remove from shared once each chunk is done.
+        let mut expiring = vec![Vec::new(); num_chunks.iter().product()];
+        let mut current = 0;
+        for z in 0..num_chunks[2] {
+            for y in 0..num_chunks[1] {
+                for x in 0..num_chunks[0] {
+                    let lo = [x, y, z].map(|c| CHUNK_PADDING + c * chunk_size);
+                    let hi = std::array::from_fn(|i| {
+                        cmp::min(lo[i] + chunk_size, CHUNK_PADDING + cells[i])
+                    });
+                    let mut chunk = self.chunk_tessellator(Chunk { lo, hi });
+                    let mut local = Mesh::default();
+                    chunk.tessellate_grid(&mut local, &mut |_| {});
+                    let shared_vertices = chunk.shared_vertices.into_inner();
+                    let global_index: Vec<usize> = local
+                        .vertices
+                        .iter()
+                        .enumerate()
+                        .map(|(i, &v)| {
+                            // Forward the attributes sampled by the chunk.
+                            let mut add_vertex = || match local.attribute_stride {
+                                0 => sink.add_vertex(v),
+                                _ => sink.add_vertex_with_attributes(v, local.attributes(i)),
+                            };
+                            match shared_vertices.get(&i) {
+                                Some(key) => *shared.entry(*key).or_insert_with(|| {
+                                    expiring[last_chunk(key)].push(*key);
+                                    add_vertex()
+                                }),
+                                None => add_vertex(),
+                            }
+                        })
+                        .collect();
+                    add_faces(sink, &local, &global_index);
+                    for key in std::mem::take(&mut expiring[current]) {
+                        shared.remove(&key);
+                    }
+                    current += 1;
+                }
+            }
+        }
+        debug_assert!(shared.is_empty());
+    }
+
+    /// Tessellate a function that repeats with the period `period` along all axes and repeat the
+    /// result `tiles` times along each axis.
+    ///
+    /// The function is only evaluated within one period and the grid wraps around, so all tiles
+    /// are identical. Vertices on tile boundaries are shared by the neighboring tiles, so the
+    /// tiling is watertight except for its outer boundary, where it is cut open. With a single
+    /// tile, the vertices on opposite faces of `period` match up when the tile is repeated.
+    ///
+    /// The bounds of the function are not searched. The resolution is adjusted, so that every
+    /// period spans a whole number of cells. The mesh is not simplified across tile boundaries.
+    pub fn tessellate_periodic(
+        &mut self,
+        period: &BoundingBox<S, 3>,
+        tiles: [usize; 3],
+    ) -> Mesh<S> {
+        let extent = period.max - period.min;
+        let cells: Index = std::array::from_fn(|i| {
+            cmp::max(Float::round(extent[i] / self.res[i]).as_usize(), 1)
+        });
+        self.res = extent.component_div(&na::Vector3::from(cells.map(|c| From::from(c as f32))));
+        self.options.res = self.res;
+        let padding: S = From::from(CHUNK_PADDING as f32);
+        self.origin = period.min - self.res * padding;
+        self.set_dim(cells.map(|c| c + 2 * CHUNK_PADDING));
+        self.period = Some(cells);
+        self.face_budget_met = None;
+
+        let mut tile = self.chunk_tessellator(Chunk {
+            lo: [CHUNK_PADDING; 3],
+            hi: cells.map(|c| CHUNK_PADDING + c),
+        });
+        // Attributes are sampled at the final positions in all tiles instead.
+        tile.attributes = None;
+        let mut local = Mesh::default();
+        tile.tessellate_grid(&mut local, &mut |_| {});
+        let shared_vertices = tile.shared_vertices.into_inner();
+
+        // Cell of a shared vertex relative to the first tile, and the number of periods it is
+        // away from the first period.
+        let relative_cell =
+            |vi: &VertexIndex| vi.index.map(|i| i as isize - CHUNK_PADDING as isize);
+        let wrap = |cell: [isize; 3]| -> [isize; 3] {
+            std::array::from_fn(|i| cell[i].rem_euclid(cells[i] as isize))
+        };
+        let translation = |shift: [isize; 3]| {
+            extent.component_mul(&na::Vector3::from(shift.map(|s| From::from(s as f32))))
+        };
+        let periods = |cell: [isize; 3]| {
+            translation(std::array::from_fn(|i| cell[i].div_euclid(cells[i] as isize)))
+        };
+        // All copies of a shared vertex get their position from the same representative in the
+        // first period, so they match exactly. The copies differ in the last bits, so the
+        // representative is picked deterministically: the copy in the first period, if there is
+        // one, otherwise the one in the lowest cell.
+        let mut copies: Vec<(usize, [isize; 3])> = shared_vertices
+            .iter()
+            .map(|(&i, vi)| (i, relative_cell(vi)))
+            .collect();
+        copies.sort_by_key(|&(i, cell)| (wrap(cell) != cell, cell, i));
+        let mut representative = HashMap::new();
+        for (i, cell) in copies {
+            let vi = &shared_vertices[&i];
+            let v = local.vertices[i];
+            representative
+                .entry((vi.edges, wrap(cell)))
+                .or_insert_with(|| na::Point3::new(v[0], v[1], v[2]) - periods(cell));
+        }
+
+        let mut mesh = Mesh::default();
+        let mut shared = HashMap::new();
+        for z in 0..tiles[2] {
+            for y in 0..tiles[1] {
+                for x in 0..tiles[0] {
+                    let t = [x, y, z];
+                    let offset = translation(t.map(|t| t as isize));
+                    let global_index: Vec<usize> = local
+                        .vertices
+                        .iter()
+                        .enumerate()
+                        .map(|(i, v)| match shared_vertices.get(&i) {
+                            Some(vi) => {
+                                let rel = relative_cell(vi);
+                                let cell: [isize; 3] =
+                                    std::array::from_fn(|i| rel[i] + (t[i] * cells[i]) as isize);
+                                *shared.entry((vi.edges, cell)).or_insert_with(|| {
+                                    let p = representative[&(vi.edges, wrap(cell))] + periods(cell);
+                                    self.emit_vertex(&mut mesh, [p.x, p.y, p.z])
+                                })
+                            }
+                            None => self.emit_vertex(
+                                &mut mesh,
+                                [v[0] + offset.x, v[1] + offset.y, v[2] + offset.z],
+                            ),
+                        })
+                        .collect();
+                    add_faces(&mut mesh, &local, &global_index);
+                }
+            }
+        }
+        mesh
+    }
+
+    // Call g with the function to tessellate, which is intersected with the clip region, if there
+    // is one.
+    fn with_function<R>(&self, g: impl FnOnce(&dyn ImplicitFunction<S>) -> R) -> R {
+        match self.options.clip {
+            Some(clip) => g(&Clipped { f: self.function, clip }),
+            None => g(self.function),
+        }
+    }
+
+    // Returns a tessellator for one chunk of the grid of self.
+    fn chunk_tessellator(&self, chunk: Chunk) -> ManifoldDualContouring<'a, S> {
+        ManifoldDualContouring {
+            origin: self.origin,
+            dim: self.dim,
+            chunk: Some(chunk),
+            period: self.period,
+            attributes: self.attributes,
+            thread_pool: self.thread_pool.clone(),
+            ..ManifoldDualContouring::with_options(self.function, self.options)
+        }
+    }
+
+    // Add v to sink, together with its attributes, if they are sampled.
+    fn emit_vertex(&self, sink: &mut dyn MeshSink<S>, v: [S; 3]) -> usize {
+        match &self.attributes {
+            Some(attributes) => {
+                sink.add_vertex_with_attributes(v, &attributes.sample(&na::Point3::from(v)))
+            }
+            None => sink.add_vertex(v),
+        }
+    }
+
+    // Find the bounds of the function and set up origin and dimensions of the grid.
+    pub(crate) fn find_grid(&mut self) {
+        let bbox = self.with_function(|f| find_bounds(f, &self.options));
+        let padding = self.options.grid_padding();
+        self.origin = bbox.min - padding;
+        if let Some(lattice) = self.options.lattice_origin {
+            // Move the origin down onto the lattice.
+            let steps = (self.origin - lattice).component_div(&self.res).map(Float::floor);
+            self.origin = lattice + steps.component_mul(&self.res);
+        }
+        let extent = bbox.max + padding - self.origin;
+        self.set_dim(std::array::from_fn(|i| Float::ceil(extent[i] / self.res[i]).as_usize()));
+    }
+
+    // Set the dimensions of the grid. Panics, if the grid is too large for the keys of the sparse
+    // grids, which would silently alias cells otherwise. Sampling covers a cube with a power of two
+    // points per axis, and the edge grid packs three edges per point along x.
+    fn set_dim(&mut self, dim: [usize; 3]) {
+        let sampled = pow2roundup(cmp::max(dim.into_iter().max().unwrap_or(0), 1));
+        assert!(
+            sampled * 3 <= GRID_LIMIT,
+            "grid of {:?} cells exceeds the maximum of {} cells per axis, use a coarser resolution",
+            dim,
+            GRID_LIMIT / 4
+        );
+        self.dim = dim;
+    }
+
+    fn build_value_grid(&mut self, progress: &mut impl FnMut(ProgressEvent)) {
+        if self.period.is_some() {
+            return self.build_periodic_value_grid(progress);
+        }
+        let maxdim = cmp::max(self.dim[0], cmp::max(self.dim[1], self.dim[2]));
+        let origin = self.origin;
+        let origin_value = self.with_function(|f| f.value(&origin));
+        let total = self.dim[0] * self.dim[1] * self.dim[2];
+        let mut done = 0usize;
+        self.sample_value_grid([0, 0, 0], origin, pow2roundup(maxdim), origin_value, &mut done, total, progress);
+    }
+
+    // Sample all grid points of the chunk densely, evaluating the function within the first period
+    // only.
+    fn build_periodic_value_grid(&mut self, progress: &mut impl FnMut(ProgressEvent)) {
+        let chunk = self.chunk.expect("periodic mode requires a chunk");
+        let lo = chunk.lo.map(|i| i - CHUNK_PADDING);
+        let hi = chunk.hi.map(|i| i + CHUNK_PADDING);
+        let total = (0..3).map(|i| hi[i] - lo[i]).product();
+        let mut done = 0;
+        for z in lo[2]..hi[2] {
+            for y in lo[1]..hi[1] {
+                for x in lo[0]..hi[0] {
+                    let p = self.grid_point(self.wrap([x, y, z]));
+                    let value = self.with_function(|f| f.value(&p));
+                    self.value_grid.insert([x, y, z], value);
+                    done += 1;
+                    progress(ProgressEvent::SamplingGrid { done, total });
+                }
+            }
+        }
+    }
+
+    // In periodic mode, return the grid point in the first period, that is equivalent to idx.
+    fn wrap(&self, idx: Index) -> Index {
+        match self.period {
+            Some(period) => std::array::from_fn(|i| {
+                let relative = idx[i] as isize - CHUNK_PADDING as isize;
+                CHUNK_PADDING + relative.rem_euclid(period[i] as isize) as usize
+            }),
+            None => idx,
+        }
+    }
+
+    // This method does the main work of tessellation.
+    fn tessellate_grid(&mut self, sink: &mut dyn MeshSink<S>, progress: &mut impl FnMut(ProgressEvent)) {
+        self.sample_surface(progress);
+        self.build_octree(progress);
+        self.merge_rank.clear();
+        self.face_budget_met = None;
+        match self.options.face_budget {
+            Some(budget) if self.chunk.is_none() => {
+                self.face_budget_met = Some(self.merge_for_budget(budget))
+            }
+            _ => self.solve_qefs(progress),
+        }
+        let face_count = self.generate_quads(sink, progress);
+        progress(ProgressEvent::Done { face_count });
+    }
+
+    // Merge the octree vertices in order of increasing error, until the mesh has at most budget
+    // faces. Vertices are ranked by their error and only the merge_count cheapest ones may be
+    // merged. The smallest merge_count within budget is binary searched, with a full pass of
+    // generate_quads over the octree per probe, so O(log n) passes for n vertices. The face count
+    // is not guaranteed to fall monotonically with merge_count, as the manifold check can stop a
+    // merge that it allowed with fewer merged vertices. So merge_count is within budget, if any
+    // probe was, but not necessarily the smallest such count. Returns whether the budget was met.
+    // Otherwise all vertices may be merged.
+    fn merge_for_budget(&mut self, budget: usize) -> bool {
+        // lookup_cell_point never climbs into the top layer.
+        let mut order: Vec<(S, usize, usize)> = Vec::new();
+        for layer in 1..self.vertex_octtree.len().saturating_sub(1) {
+            for (i, vertex) in self.vertex_octtree[layer].iter().enumerate() {
+                let mut qef = vertex.qef.borrow_mut();
+                if qef.error.is_nan() {
+                    qef.solve();
+                }
+                order.push((qef.error, layer, i));
+            }
+        }
+        order.sort_by(|a, b| a.0.total_cmp(&b.0).then_with(|| (a.1, a.2).cmp(&(b.1, b.2))));
+        self.merge_rank = self.vertex_octtree.iter().map(|l| vec![usize::MAX; l.len()]).collect();
+        for (rank, &(_, layer, i)) in order.iter().enumerate() {
+            self.merge_rank[layer][i] = rank;
+        }
+
+        // Mostly fewer faces for more merged vertices, so binary search for the first count
+        // within budget.
+        let mut count_faces = |count: usize| {
+            self.merge_count = count;
+            for vertex in self.vertex_octtree.iter().flatten() {
+                vertex.mesh_index.set(None);
+            }
+            let mut counter = FaceCounter::default();
+            self.generate_quads(&mut counter, &mut |_| {});
+            counter.faces
+        };
+        let (mut lo, mut hi) = (0, order.len());
+        while lo < hi {
+            let mid = (lo + hi) / 2;
+            if count_faces(mid) <= budget {
+                hi = mid;
+            } else {
+                lo = mid + 1;
+            }
+        }
+        let met = lo < order.len() || count_faces(lo) <= budget;
+        self.merge_count = lo;
+        for vertex in self.vertex_octtree.iter().flatten() {
+            vertex.mesh_index.set(None);
+        }
+        // Later edits merge by the error of the most expensive merged vertex.
+        self.error = lo
+            .checked_sub(1)
+            .map_or(Float::neg_infinity(), |last| order[last].0);
+        met
+    }
+
+    // Returns whether the octree vertex index of layer is cheap enough to replace its children.
+    fn is_cheap_enough(&self, layer: usize, index: usize, error: S) -> bool {
+        match self.merge_rank.get(layer) {
+            Some(ranks) => ranks[index] < self.merge_count,
+            None => error <= self.error,
+        }
+    }
+
+    // Generate the leaf vertices from the edge grid and build the octree above them.
+    fn build_octree(&mut self, progress: &mut impl FnMut(ProgressEvent)) {
+        let (leafs, index_map) = self.generate_leaf_vertices(progress);
+        self.vertex_index_map = index_map;
+        self.vertex_octtree.push(leafs);
+
+        let mut layer = 0usize;
+        // Without simplification the octree consists of the leafs only.
+        while self.options.simplification {
+            progress(ProgressEvent::OctreeLayer { layer });
+            let base = self.vertex_octtree.last().unwrap();
+            let next = subsample_octtree(base, 0..base.len(), 0);
+            if next.len() == self.vertex_octtree.last().unwrap().len() {
+                break;
+            }
+            self.vertex_octtree.push(next);
+            layer += 1;
+        }
+    }
+
+    // Generate the quads of all edges owned by self, add them to sink and return the number of
+    // triangles added.
+    fn generate_quads(&self, sink: &mut dyn MeshSink<S>, progress: &mut impl FnMut(ProgressEvent)) -> usize {
+        let total_quads = self.edge_grid.borrow().len();
+        let mut face_count = 0;
+        for (i, edge_index) in self.edge_grid.borrow().keys().enumerate() {
+            progress(ProgressEvent::GeneratingQuad { done: i + 1, total: total_quads });
+            if self.owns_edge(edge_index.index) {
+                face_count += self.compute_quad(edge_index, sink);
+            }
+        }
+        face_count
+    }
+
+    // Sample the value grid, compact it and find all edge crossings. These are the stages shared
+    // by all extractors.
+    pub(crate) fn sample_surface(&mut self, progress: &mut impl FnMut(ProgressEvent)) {
+        self.build_value_grid(progress);
+
+        let compact_total = self.value_grid.len();
+        progress(ProgressEvent::CompactingGrid { done: 0, total: compact_total });
+        self.compact_value_grid();
+        progress(ProgressEvent::CompactingGrid { done: compact_total, total: compact_total });
+
+        self.generate_edge_grid(progress);
+    }
+
+    // Returns true, if the edges starting at idx are tessellated by self.
+    fn owns_edge(&self, idx: Index) -> bool {
+        self.chunk.is_none_or(|c| c.owns(idx))
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn sample_value_grid(
+        &mut self,
+        idx: Index,
+        pos: na::Point3<S>,
+        size: usize,
+        val: S,
+        done: &mut usize,
+        total: usize,
+        progress: &mut impl FnMut(ProgressEvent),
+    ) {
+        debug_assert!(size > 1);
+        let mut midx = idx;
+        let size = size / 2;
+        let size_s: S = From::from(size as f32);
+        let vpos = [
+            pos,
+            pos + self.res * size_s,
+        ];
+
+        for z in 0..2 {
+            for y in 0..2 {
+                for x in 0..2 {
+                    // In chunked mode, skip all sub-cubes outside of the chunk.
+                    if self.chunk.is_none_or(|c| c.overlaps(midx, size)) {
+                        let mpos = na::Point3::new(vpos[x].x, vpos[y].y, vpos[z].z);
+                        let value = if midx == idx {
+                            val
+                        } else {
+                            self.with_function(|f| f.value(&mpos))
+                        };
+
+                        if size > 1 && self.may_contain_surface(mpos, size_s, value) {
+                            match self.flat_plane(mpos, size_s, value) {
+                                Some(plane) => self.fill_value_grid(midx, mpos, size, &plane, done, total, progress),
+                                None => self.sample_value_grid(midx, mpos, size, value, done, total, progress),
+                            };
+                        } else if self.chunk.is_none_or(|c| c.samples(midx)) {
+                            self.value_grid.insert(midx, value);
+                            *done += 1;
+                            progress(ProgressEvent::SamplingGrid { done: *done, total });
+                        }
+                    }
+                    midx[0] += size;
+                }
+                midx[0] -= 2 * size;
+                midx[1] += size;
+            }
+            midx[1] -= 2 * size;
+            midx[2] += size;
+        }
+    }
+
+    // Returns whether the sub-cube at pos with edge length size * res might contain a part of the
+    // surface. val is the function value at pos. Uses the interval of the function, if it
+    // provides one, and the distance property of val otherwise.
+    fn may_contain_surface(&self, pos: na::Point3<S>, size: S, val: S) -> bool {
+        // Include the edges from the neighbors below, since the grid points of a skipped sub-cube
+        // are missing for them as well.
+        let bbox = BoundingBox::new(&(pos - self.res), &(pos + self.res * size));
+        let (lo, hi) = self.with_function(|f| f.interval(&bbox));
+        let max: S = Float::max_value();
+        if lo > -max || hi < max {
+            is_inside(lo) && !is_inside(hi)
+        } else {
+            Float::abs(val) / self.with_function(|f| f.lipschitz()) <= (self.res * size).norm()
+        }
+    }
+
+    // If adaptive sampling is enabled and the function is flat within the sub-cube at pos with
+    // edge length size * res, return the plane along the mean normal of the samples. Only its
+    // offset is fitted to the values. val is the function value at pos.
+    fn flat_plane(&self, pos: na::Point3<S>, size: S, val: S) -> Option<Plane<S>> {
+        let adaptive = self.options.adaptive?;
+        if size < From::from(MIN_FLAT_SIZE as f32) {
+            return None;
+        }
+        let half: S = From::from(0.5f32);
+        let extent = self.res * size;
+        let mut points = Vec::with_capacity(9);
+        for corner in 0..8 {
+            let mut p = pos;
+            for dim in 0..3 {
+                if corner & (1 << dim) != 0 {
+                    p[dim] += extent[dim];
+                }
+            }
+            points.push(p);
+        }
+        points.push(pos + extent * half);
+
+        let (values, normals): (Vec<S>, Vec<na::Vector3<S>>) = self.with_function(|f| {
+            points
+                .iter()
+                .enumerate()
+                .map(|(i, p)| (if i == 0 { val } else { f.value(p) }, f.normal(p)))
+                .unzip()
+        });
+        let n = normals
+            .iter()
+            .fold(na::Vector3::zeros(), |sum: na::Vector3<S>, n| sum + n)
+            .try_normalize(From::from(EPSILON))?;
+        let max_error = self.options.min_res() * adaptive.relative_error;
+        if !normals.iter().all(|ni| ni.dot(&n) >= adaptive.min_cos) {
+            return None;
+        }
+        let num: S = From::from(points.len() as f32);
+        let mean_point = points
+            .iter()
+            .fold(na::Vector3::zeros(), |sum: na::Vector3<S>, p| sum + p.coords)
+            / num;
+        let mean_value = values.iter().fold(From::from(0f32), |sum: S, &v| sum + v) / num;
+        let plane = Plane {
+            p: na::Point3::from(mean_point - n * mean_value),
+            n,
+        };
+        let fits = points
+            .iter()
+            .zip(values.iter())
+            .all(|(p, &v)| Float::abs(plane.n.dot(&(p - plane.p)) - v) <= max_error);
+        if fits { Some(plane) } else { None }
+    }
+
+    // Fill the sub-cube starting at idx/pos with edge length size from plane instead of sampling
+    // the function. Only values close enough to the plane to be relevant for edge crossings are
+    // stored.
+    #[allow(clippy::too_many_arguments)]
+    fn fill_value_grid(
+        &mut self,
+        idx: Index,
+        pos: na::Point3<S>,
+        size: usize,
+        plane: &Plane<S>,
+        done: &mut usize,
+        total: usize,
+        progress: &mut impl FnMut(ProgressEvent),
+    ) {
+        let two: S = From::from(2f32);
+        let max_dist = two * self.res.norm();
+        for z in 0..size {
+            for y in 0..size {
+                for x in 0..size {
+                    let p = pos
+                        + na::Vector3::new(
+                            From::from(x as f32),
+                            From::from(y as f32),
+                            From::from(z as f32),
+                        )
+                        .component_mul(&self.res);
+                    let value = plane.n.dot(&(p - plane.p));
+                    let point_idx = offset(idx, [x, y, z]);
+                    if Float::abs(value) <= max_dist && self.chunk.is_none_or(|c| c.samples(point_idx)) {
+                        self.value_grid.insert(point_idx, value);
+                        *done += 1;
+                        progress(ProgressEvent::SamplingGrid { done: *done, total });
+                    }
+                }
+            }
+        }
+    }
+
+    // Returns the pool for the parallel stages, or None to use rayon's global pool.
+    fn thread_pool(&mut self) -> Option<Arc<rayon::ThreadPool>> {
+        let threads = self.options.threads;
+        if threads == 0 {
+            return None;
+        }
+        let pool = self.thread_pool.get_or_insert_with(|| {
+            Arc::new(
+                rayon::ThreadPoolBuilder::new()
+                    .num_threads(threads)
+                    .build()
+                    .expect("could not create thread pool"),
+            )
+        });
+        Some(pool.clone())
+    }
+
+    // Delete all values from value grid that do not have a value of opposing signum in any
+    // neighboring index.
+    // This might reduces memory usage by ~10x.
+    fn compact_value_grid(&mut self) {
+        let thread_pool = self.thread_pool();
+        // Decide for all indexes whether to keep them.
+        let value_grid = &mut self.value_grid;
+        let decide = || -> Vec<bool> {
+            value_grid
+                .par_iter()
+                .map(|(idx, &v)| {
+                    if idx[0] == 0 || idx[1] == 0 || idx[2] == 0 {
+                        // This grid cell does not have neighbors in some directions. Ignore.
+                        return true;
+                    }
+                    for z in 0..3 {
+                        for y in 0..3 {
+                            for x in 0..3 {
+                                let adjacent_idx = [idx[0] + x - 1, idx[1] + y - 1, idx[2] + z - 1];
+                                if let Some(&adjacent_value) = value_grid.get(&adjacent_idx)
+                                    && is_inside(v) != is_inside(adjacent_value)
+                                {
+                                    // Keep indexes with opposing signum.
+                                    return true;
+                                }
+                            }
+                        }
+                    }
+                    false
+                })
+                .collect()
+        };
+        let keep = match thread_pool {
+            Some(pool) => pool.install(decide),
+            None => decide(),
+        };
+        // retain visits the values in the same order as par_iter.
+        let mut keep = keep.into_iter();
+        value_grid.retain(|_, _| keep.next().unwrap());
+    }
+
+    // Returns the position of the grid point idx.
+    fn grid_point(&self, idx: Index) -> na::Point3<S> {
+        self.origin
+            + na::Vector3::new(
+                From::from(idx[0] as f32),
+                From::from(idx[1] as f32),
+                From::from(idx[2] as f32),
+            )
+            .component_mul(&self.res)
     }
 
     // Store crossing positions of edges in edge_grid
     fn generate_edge_grid(&mut self, progress: &mut impl FnMut(ProgressEvent)) {
         let total = self.value_grid.len();
         let mut edge_grid = self.edge_grid.borrow_mut();
-        for (done, (&point_idx, &point_value)) in self.value_grid.iter().enumerate() {
+        for (done, (point_idx, &point_value)) in self.value_grid.iter().enumerate() {
             progress(ProgressEvent::GeneratingEdges { done: done + 1, total });
-            for &edge in &[Edge::A, Edge::B, Edge::C] {
-                let mut adjacent_idx = point_idx;
-                adjacent_idx[edge as usize] += 1;
-                if let Some(&adjacent_value) = self.value_grid.get(&adjacent_idx) {
-                    let point_pos = self.origin
-                        + na::Vector3::new(
-                            From::from(point_idx[0] as f32),
-                            From::from(point_idx[1] as f32),
-                            From::from(point_idx[2] as f32),
-                        ) * self.res;
-                    let mut adjacent_pos = point_pos;
-                    adjacent_pos[edge as usize] += self.res;
-                    if let Some(plane) =
-                        self.find_zero(point_pos, point_value, adjacent_pos, adjacent_value)
-                    {
-                        edge_grid.insert(
-                            EdgeIndex {
-                                edge,
-                                index: point_idx,
-                            },
-                            plane,
-                        );
+            self.insert_edges(&mut edge_grid, point_idx, point_value);
+        }
+    }
+
+    // Store the crossing positions of the three edges starting at point_idx in edge_grid.
+    fn insert_edges(
+        &self,
+        edge_grid: &mut SparseGrid<EdgeIndex, Crossing<S>>,
+        point_idx: Index,
+        point_value: S,
+    ) {
+        // In periodic mode, the crossing is searched in the first period and moved back.
+        let wrapped_idx = self.wrap(point_idx);
+        for &edge in &[Edge::A, Edge::B, Edge::C] {
+            let mut adjacent_idx = point_idx;
+            adjacent_idx[edge as usize] += 1;
+            if let Some(&adjacent_value) = self.value_grid.get(&adjacent_idx) {
+                let point_pos = self.grid_point(wrapped_idx);
+                let mut adjacent_pos = point_pos;
+                adjacent_pos[edge as usize] += self.res[edge as usize];
+                if let Some(mut plane) =
+                    self.find_zero(point_pos, point_value, adjacent_pos, adjacent_value)
+                {
+                    let tag = self.with_function(|f| f.tag(&plane.p));
+                    if wrapped_idx != point_idx {
+                        plane.p += self.grid_point(point_idx) - point_pos;
                     }
+                    edge_grid.insert(
+                        EdgeIndex {
+                            edge,
+                            index: point_idx,
+                        },
+                        Crossing { plane, tag },
+                    );
                 }
             }
         }
@@ -1019,17 +2252,26 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
 
     // Generates leaf vertices along with a map that points VertexIndices to the index in the leaf
     // vertex vec.
-    fn generate_leaf_vertices(&self, progress: &mut impl FnMut(ProgressEvent)) -> (Vec<Vertex<S>>, HashMap<VertexIndex, usize>) {
-        let mut index_map = HashMap::new();
+    fn generate_leaf_vertices(&self, progress: &mut impl FnMut(ProgressEvent)) -> (Vec<Vertex<S>>, FastHashMap<VertexIndex, usize>) {
+        let mut index_map = FastHashMap::default();
         let mut vertices = Vec::new();
-        let edge_keys: Vec<EdgeIndex> = self.edge_grid.borrow().keys().copied().collect();
+        let edge_keys: Vec<EdgeIndex> = self.edge_grid.borrow().keys().collect();
         let total = edge_keys.len();
         for (i, edge_index) in edge_keys.iter().enumerate() {
             progress(ProgressEvent::GeneratingVerts { done: i + 1, total });
-            self.add_vertices_for_minimal_egde(edge_index, &mut vertices, &mut index_map);
+            if self.owns_edge(edge_index.index) {
+                self.add_vertices_for_minimal_egde(edge_index, &mut vertices, &mut index_map);
+            }
         }
         for vertex in &mut vertices {
             for neighbor_vec in &mut vertex.neighbors {
+                if self.chunk.is_some() {
+                    // Neighbors outside of the chunk do not have a vertex.
+                    neighbor_vec.retain(|neighbor| match neighbor {
+                        VarIndex::VertexIndex(vi) => index_map.contains_key(vi),
+                        VarIndex::Index(_) => true,
+                    });
+                }
                 for neighbor in neighbor_vec.iter_mut() {
                     match *neighbor {
                         VarIndex::VertexIndex(vi) => *neighbor = VarIndex::Index(index_map[&vi]),
@@ -1069,13 +2311,10 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
         &self,
         edge_index: &EdgeIndex,
         vertices: &mut Vec<Vertex<S>>,
-        index_map: &mut HashMap<VertexIndex, usize>,
+        index_map: &mut FastHashMap<VertexIndex, usize>,
     ) {
-        debug_assert!((edge_index.edge as usize) < 4);
-        let cell_size = na::Vector3::new(self.res, self.res, self.res);
-        for &quad_egde in &QUADS[edge_index.edge as usize] {
-            let idx = neg_offset(edge_index.index, EDGE_OFFSET[quad_egde as usize]);
-
+        let cell_size = self.res;
+        for (quad_egde, idx) in quad_cells(*edge_index) {
             let edge_set = get_connected_edges(quad_egde, self.bitset_for_cell(idx));
             let vertex_index = VertexIndex {
                 edges: edge_set,
@@ -1116,12 +2355,7 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
                         })
                     })
                     .collect();
-                let cell_origin = self.origin
-                    + na::Vector3::new(
-                        From::from(idx[0] as f32),
-                        From::from(idx[1] as f32),
-                        From::from(idx[2] as f32),
-                    ) * self.res;
+                let cell_origin = self.grid_point(idx);
                 vertices.push(Vertex {
                     index: idx,
                     qef: RefCell::new(qef::Qef::new(
@@ -1134,6 +2368,8 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
                     mesh_index: Cell::new(None),
                     edge_intersections: intersections,
                     euler_characteristic: 1,
+                    pinned: self.chunk.is_some_and(|c| c.is_boundary_cell(idx)),
+                    removed: false,
                 });
                 vertices.len() - 1
             });
@@ -1141,8 +2377,8 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
     }
 
     fn get_edge_tangent_plane(&self, edge_index: &EdgeIndex) -> Plane<S> {
-        if let Some(plane) = self.edge_grid.borrow().get(&edge_index.base()) {
-            return *plane;
+        if let Some(crossing) = self.edge_grid.borrow().get(&edge_index.base()) {
+            return crossing.plane;
         }
         panic!(
             "could not find edge_point: {:?} -> {:?}",
@@ -1151,8 +2387,8 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
         );
     }
 
-    // Return the Point index (in self.mesh.vertices) the the point belonging to edge/idx.
-    fn lookup_cell_point(&self, edge: Edge, idx: Index) -> usize {
+    // Return the Point index (in sink) the the point belonging to edge/idx.
+    fn lookup_cell_point(&self, edge: Edge, idx: Index, sink: &mut dyn MeshSink<S>) -> usize {
         // Generate the proper vertex Index from a single edge and an Index.
         let edge_set = get_connected_edges(edge, self.bitset_for_cell(idx));
         let vertex_index = VertexIndex {
@@ -1163,19 +2399,25 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
         // Convert the vertex index to index and layer in the Octtree.
         let mut octtree_index = self.vertex_index_map[&vertex_index];
         let mut octtree_layer = 0;
-        // Walk up the chain of parents
-        loop {
+        // Walk up the chain of parents, but stop before we reach the top.
+        while octtree_layer + 2 < self.vertex_octtree.len() && octtree_layer < self.max_layer {
             let next_index = self.vertex_octtree[octtree_layer][octtree_index]
                 .parent
                 .get()
                 .unwrap();
             let next_vertex = &self.vertex_octtree[octtree_layer + 1][next_index];
+            if next_vertex.qef.borrow().error.is_nan() {
+                // The qef was not solved, since the error in a layer above was below the
+                // threshold. Solve it now, so that all children of this vertex take the same
+                // decision - no matter in which order they are looked up.
+                next_vertex.qef.borrow_mut().solve()
+            }
             let error = next_vertex.qef.borrow().error;
-            if (!error.is_nan() && error > (self.error))
-                || (octtree_layer == self.vertex_octtree.len() - 2)
-                || !next_vertex.is_2manifold()
+            if !self.is_cheap_enough(octtree_layer + 1, next_index, error)
+                || (self.options.manifold_check && !next_vertex.is_2manifold())
+                || next_vertex.pinned
             {
-                // Stop, if either the error is too large or we will reach the top.
+                // Stop, if either the error is too large or the parent must not be used.
                 break;
             }
             octtree_layer += 1;
@@ -1192,11 +2434,23 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
             // threshold. But it seems, manifold criterion has catched and we need to solve it now.
             vertex.qef.borrow_mut().solve()
         }
-        let qef_solution = vertex.qef.borrow().solution;
-        let vertex_list = &mut self.mesh.borrow_mut().vertices;
-        let result = vertex_list.len();
+        let mut p = na::Point3::from(vertex.qef.borrow().solution);
+        if let Some(tolerance) = self.options.projection {
+            let mut cell = vertex.qef.borrow().bbox.clone();
+            cell.dilate(tolerance * self.options.min_res());
+            let max_step = (cell.max - cell.min).norm();
+            let projected = self.with_function(|f| project_to_surface(f, p, max_step));
+            if cell.contains(&projected) {
+                p = projected;
+            }
+        }
+        let result = self.emit_vertex(sink, [p.x, p.y, p.z]);
         vertex.mesh_index.set(Some(result));
-        vertex_list.push([qef_solution.x, qef_solution.y, qef_solution.z]);
+        if vertex.pinned {
+            // Only leafs can be pinned vertices in the mesh.
+            debug_assert_eq!(octtree_layer, 0);
+            self.shared_vertices.borrow_mut().insert(result, vertex_index);
+        }
         result
     }
 
@@ -1207,7 +2461,7 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
             for y in 0..2 {
                 for x in 0..2 {
                     if let Some(&v) = self.value_grid.get(&idx) {
-                        if v < From::from(0f32) {
+                        if is_inside(v) {
                             result.set(z << 2 | y << 1 | x);
                         }
                     } else {
@@ -1224,39 +2478,54 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
         result
     }
 
-    // Compute a quad for the given edge and append it to the list.
-    fn compute_quad(&self, edge_index: EdgeIndex) {
-        debug_assert!((edge_index.edge as usize) < 4);
+    // Compute a quad for the given edge, add it to sink and return the number of triangles added.
+    fn compute_quad(&self, edge_index: EdgeIndex, sink: &mut dyn MeshSink<S>) -> usize {
         debug_assert!(edge_index.index.iter().all(|&i| i > 0));
 
         let mut p = Vec::with_capacity(4);
-        for &quad_egde in &QUADS[edge_index.edge as usize] {
-            let point_index = self.lookup_cell_point(
-                quad_egde,
-                neg_offset(edge_index.index, EDGE_OFFSET[quad_egde as usize]),
-            );
+        for (quad_egde, cell) in quad_cells(edge_index) {
+            let point_index = self.lookup_cell_point(quad_egde, cell, sink);
             // Dedup points before insertion (two minimal vertices might end up in the same parent
             // vertex).
             if !p.contains(&point_index) {
                 p.push(point_index)
             }
         }
-        // Only try to generate meshes, if there are more then two points.
-        if p.len() < 3 {
-            return;
-        }
         // Reverse order, if the edge is reversed.
-        if let Some(&v) = self.value_grid.get(&edge_index.index)
-            && v < From::from(0f32)
-        {
-            p.reverse();
-        }
-        let face_list = &mut self.mesh.borrow_mut().faces;
-        // TODO: Fix this to choose the proper split.
-        face_list.push([p[0], p[1], p[2]]);
-        if p.len() == 4 {
-            face_list.push([p[2], p[3], p[0]]);
+        let reverse = self
+            .value_grid
+            .get(&edge_index.index)
+            .is_some_and(|&v| is_inside(v));
+        let tag = self.edge_grid.borrow()[&edge_index].tag;
+        let tag = tag.map(|tag| self.vote_tag(edge_index, tag));
+        push_quad(sink, &mut p, reverse, tag)
+    }
+
+    // Returns the most frequent tag of the crossings in the four cells around edge_index. Each
+    // crossing is counted once per cell it is connected to the quad in. The crossing of the quad
+    // itself is connected in all four cells and wins ties, so single crossings with a different
+    // tag don't spread to their neighbors.
+    fn vote_tag(&self, edge_index: EdgeIndex, own_tag: u32) -> u32 {
+        let edge_grid = self.edge_grid.borrow();
+        let mut votes: Vec<(u32, usize)> = Vec::new();
+        for (quad_edge, cell) in quad_cells(edge_index) {
+            for edge in get_connected_edges(quad_edge, self.bitset_for_cell(cell)) {
+                let edge_index = EdgeIndex {
+                    edge: Edge::from_usize(edge),
+                    index: cell,
+                };
+                if let Some(tag) = edge_grid.get(&edge_index.base()).and_then(|c| c.tag) {
+                    match votes.iter_mut().find(|(t, _)| *t == tag) {
+                        Some((_, count)) => *count += 1,
+                        None => votes.push((tag, 1)),
+                    }
+                }
+            }
         }
+        votes
+            .into_iter()
+            .max_by_key(|&(tag, count)| (count, tag == own_tag))
+            .map_or(own_tag, |(tag, _)| tag)
     }
 
     // If a is inside the object and b outside - this method returns the point on the line between
@@ -1264,25 +2533,25 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
     // av and bv represent the object values at a and b.
     fn find_zero(&self, a: na::Point3<S>, av: S, b: na::Point3<S>, bv: S) -> Option<Plane<S>> {
         assert!(a != b);
-        if Float::signum(av) == Float::signum(bv) {
+        if is_inside(av) == is_inside(bv) {
             return None;
         }
 
         let d = b - a; // edge vector; all movement stays on this line
         let d_norm = d.norm();
-        let prec: S = From::from(PRECISION);
-        let precision = prec * self.res;
+        let precision = self.options.precision * d_norm;
         let zero: S = From::from(0f32);
         let one: S = From::from(1f32);
         let half: S = From::from(0.5f32);
         let eps: S = From::from(1e-3f32);
 
-        // Bracket invariant: lo_v <= 0 <= hi_v throughout.
+        // Bracket invariant: lo is inside and hi is outside throughout. hi_v might be exactly zero,
+        // in which case hi is returned right away.
         let (mut lo, mut lo_v, mut hi, mut hi_v) =
-            if av <= zero { (a, av, b, bv) } else { (b, bv, a, av) };
+            if is_inside(av) { (a, av, b, bv) } else { (b, bv, a, av) };
 
         // Each bisection step halves the bracket. Adjacent grid corners are
-        // distance res apart, so log2(1/PRECISION) ≈ 5 bisections suffice.
+        // distance res apart, so log2(1/precision) ≈ 5 bisections suffice.
         // 64 is a generous safety cap that should never be reached in practice.
         for _ in 0..64 {
             // Work from the bracket endpoint closer to zero.
@@ -1293,13 +2562,13 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
             };
 
             if Float::abs(pv) < precision || (hi - lo).norm() < precision {
-                return Some(Plane { p, n: self.function.normal(&p) });
+                return Some(Plane { p, n: self.with_function(|f| f.normal(&p)) });
             }
 
             // Newton step along the edge: p_new = p - f(p)/(normal(p)·d) * d.
             // Fall back to bisection if the projected gradient is too small
             // (surface nearly tangent to edge) or if Newton escapes the bracket.
-            let grad = self.function.normal(&p).dot(&d);
+            let grad = self.with_function(|f| f.normal(&p)).dot(&d);
             let bracket = hi - lo;
             let mid = lo + bracket * half;
             let p_new = if Float::abs(grad) > eps * d_norm {
@@ -1310,10 +2579,10 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
                 mid
             };
 
-            let new_v = self.function.value(&p_new);
+            let new_v = self.with_function(|f| f.value(&p_new));
 
-            // Update bracket, maintaining lo_v <= 0 <= hi_v.
-            if new_v <= zero {
+            // Update bracket, maintaining lo inside and hi outside.
+            if is_inside(new_v) {
                 lo = p_new;
                 lo_v = new_v;
             } else {
@@ -1321,14 +2590,14 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
                 hi_v = new_v;
             }
             debug_assert!(
-                lo_v <= zero && hi_v >= zero,
+                is_inside(lo_v) && !is_inside(hi_v),
                 "find_zero: bracket invariant violated: lo_v={lo_v:?} hi_v={hi_v:?}"
             );
         }
 
         // Max iterations reached; return the closer endpoint.
         let p = if Float::abs(lo_v) <= Float::abs(hi_v) { lo } else { hi };
-        Some(Plane { p, n: self.function.normal(&p) })
+        Some(Plane { p, n: self.with_function(|f| f.normal(&p)) })
     }
 }
 
@@ -1336,7 +2605,9 @@ impl<'a, S: From<f32> + RealField + Float + AsUSize> ManifoldDualContouring<'a,
 mod tests {
     use super::get_connected_edges_from_edge_set;
     use crate::bitset::BitSet;
-    use crate::ImplicitFunction;
+    use crate::mesh::MeshSink;
+    use crate::test_shapes::Sphere;
+    use crate::{ImplicitFunction, TessellationOptions};
     use nalgebra as na;
     //  Corner indexes
     //
@@ -1380,25 +2651,6 @@ mod tests {
     // Test shapes
     // ---------------------------------------------------------------------------
 
-    /// Sphere SDF centred at `center` with the given `radius`.
-    struct Sphere {
-        center: na::Point3<f64>,
-        radius: f64,
-    }
-    impl Sphere {
-        fn new(center: na::Point3<f64>, radius: f64) -> Self {
-            Sphere { center, radius }
-        }
-    }
-    impl super::ImplicitFunction<f64> for Sphere {
-        fn value(&self, p: &na::Point3<f64>) -> f64 {
-            (p - self.center).norm() - self.radius
-        }
-        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
-            (p - self.center).normalize()
-        }
-    }
-
     /// Torus SDF lying in the xz-plane with major radius `major` and tube
     /// radius `minor`.
     struct Torus {
@@ -1426,6 +2678,85 @@ mod tests {
         }
     }
 
+    /// Axis aligned box SDF centred at the origin with half extents `half`. Counts the number of
+    /// evaluations.
+    struct Cuboid {
+        half: na::Vector3<f64>,
+        evaluations: std::cell::Cell<usize>,
+    }
+    impl Cuboid {
+        fn new(x: f64, y: f64, z: f64) -> Self {
+            Cuboid {
+                half: na::Vector3::new(x, y, z),
+                evaluations: std::cell::Cell::new(0),
+            }
+        }
+    }
+    impl super::ImplicitFunction<f64> for Cuboid {
+        fn value(&self, p: &na::Point3<f64>) -> f64 {
+            self.evaluations.set(self.evaluations.get() + 1);
+            let q = p.coords.abs() - self.half;
+            q.sup(&na::Vector3::zeros()).norm() + q.max().min(0.0)
+        }
+        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+            let q = p.coords.abs() - self.half;
+            let n = if q.max() > 0.0 {
+                q.sup(&na::Vector3::zeros())
+            } else {
+                let mut n = na::Vector3::zeros();
+                n[q.imax()] = 1.0;
+                n
+            };
+            n.component_mul(&p.coords.map(|c| if c < 0.0 { -1.0 } else { 1.0 }))
+                .normalize()
+        }
+    }
+
+    /// Unit sphere with values scaled by `scale`, which is not a distance bound for `scale > 1`.
+    /// Provides exact intervals, if `interval` is set, and the Lipschitz constant otherwise.
+    struct ScaledSphere {
+        scale: f64,
+        interval: bool,
+    }
+    impl super::ImplicitFunction<f64> for ScaledSphere {
+        fn value(&self, p: &na::Point3<f64>) -> f64 {
+            self.scale * (p.coords.norm() - 1.0)
+        }
+        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+            p.coords.normalize()
+        }
+        fn lipschitz(&self) -> f64 {
+            if self.interval { 1. } else { self.scale }
+        }
+        fn interval(&self, bbox: &bbox::BoundingBox<f64, 3>) -> (f64, f64) {
+            if !self.interval {
+                return (f64::NEG_INFINITY, f64::INFINITY);
+            }
+            let nearest = na::Vector3::zeros().sup(&bbox.min.coords).inf(&bbox.max.coords);
+            let farthest = bbox.min.coords.abs().sup(&bbox.max.coords.abs());
+            (
+                self.scale * (nearest.norm() - 1.0),
+                self.scale * (farthest.norm() - 1.0),
+            )
+        }
+    }
+
+    /// Gyroid, which repeats every 2π along all axes.
+    struct Gyroid;
+    impl super::ImplicitFunction<f64> for Gyroid {
+        fn value(&self, p: &na::Point3<f64>) -> f64 {
+            p.x.sin() * p.y.cos() + p.y.sin() * p.z.cos() + p.z.sin() * p.x.cos()
+        }
+        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+            na::Vector3::new(
+                p.x.cos() * p.y.cos() - p.z.sin() * p.x.sin(),
+                p.y.cos() * p.z.cos() - p.x.sin() * p.y.sin(),
+                p.z.cos() * p.x.cos() - p.y.sin() * p.z.sin(),
+            )
+            .normalize()
+        }
+    }
+
     // ---------------------------------------------------------------------------
     // Helpers
     // ---------------------------------------------------------------------------
@@ -1474,6 +2805,537 @@ mod tests {
         tessellate_and_check(&Torus::new(1.0, 0.3), 0.15)
     }
 
+    #[test]
+    fn tessellate_into_writer() {
+        let sphere = Sphere::new(na::Point3::new(0.1, 0.2, -0.3), 1.0);
+        let mut mdc = super::ManifoldDualContouring::new(&sphere, 0.2, 0.1);
+        let mesh = mdc.tessellate().unwrap();
+        let mut stl = crate::StlWriter::new(std::io::Cursor::new(Vec::new())).unwrap();
+        let mut mdc = super::ManifoldDualContouring::new(&sphere, 0.2, 0.1);
+        mdc.tessellate_into(&mut stl, |_| {});
+        let data = stl.finish().unwrap().into_inner();
+        assert_eq!(data.len(), 84 + 50 * mesh.faces.len());
+    }
+
+    #[test]
+    fn adaptive_sampling_skips_flat_regions() -> Result<(), Box<dyn std::error::Error>> {
+        let cuboid = Cuboid::new(1.0, 1.0, 0.25);
+        // Returns the evaluations of the sampling stage and of the crossing search.
+        let evaluations = |adaptive: bool| {
+            let mut mdc = super::ManifoldDualContouring::new(&cuboid, 0.04, 0.0);
+            if adaptive {
+                mdc = mdc.with_adaptive_sampling(0.05, 0.01);
+            }
+            mdc.find_grid();
+            cuboid.evaluations.set(0);
+            mdc.build_value_grid(&mut |_| {});
+            let sampling = cuboid.evaluations.replace(0);
+            mdc.compact_value_grid();
+            mdc.generate_edge_grid(&mut |_| {});
+            (sampling, cuboid.evaluations.get())
+        };
+        let (uniform_sampling, uniform_crossings) = evaluations(false);
+        let (adaptive_sampling, adaptive_crossings) = evaluations(true);
+        assert!(
+            adaptive_sampling < uniform_sampling * 3 / 4,
+            "adaptive sampling used {adaptive_sampling} evaluations, uniform sampling {uniform_sampling}"
+        );
+        // Crossings are always searched with the function.
+        assert_eq!(adaptive_crossings, uniform_crossings);
+
+        let mut mdc = super::ManifoldDualContouring::new(&cuboid, 0.04, 0.0)
+            .with_adaptive_sampling(0.05, 0.01);
+        let mesh = mdc.tessellate().unwrap();
+        mesh.is_closed()?;
+        for v in &mesh.vertices {
+            let p = na::Point3::new(v[0], v[1], v[2]);
+            assert!(cuboid.value(&p).abs() < 0.04, "vertex {v:?} too far from the surface");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn deterministic_output() {
+        let torus = Torus::new(1.0, 0.3);
+        let first = super::ManifoldDualContouring::new(&torus, 0.1, 0.1).tessellate();
+        let second = super::ManifoldDualContouring::new(&torus, 0.1, 0.1).tessellate();
+        assert_eq!(first, second);
+    }
+
+    // Sphere with values truncated to multiples of step. All points closer than step to the
+    // surface evaluate to exactly zero.
+    struct QuantizedSphere {
+        sphere: Sphere,
+        step: f64,
+        zeros: std::cell::Cell<usize>,
+    }
+
+    impl ImplicitFunction<f64> for QuantizedSphere {
+        fn value(&self, p: &na::Point3<f64>) -> f64 {
+            let v = (self.sphere.value(p) / self.step).trunc() * self.step;
+            if v == 0. {
+                self.zeros.set(self.zeros.get() + 1);
+            }
+            v
+        }
+        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+            self.sphere.normal(p)
+        }
+    }
+
+    #[test]
+    fn exact_zeros_tessellate_in_one_pass() -> Result<(), Box<dyn std::error::Error>> {
+        let sphere = QuantizedSphere {
+            sphere: Sphere::new(na::Point3::origin(), 1.0),
+            step: 0.05,
+            zeros: std::cell::Cell::new(0),
+        };
+        let mut mdc = super::ManifoldDualContouring::new(&sphere, 0.2, 0.0);
+        mdc.find_grid();
+        let origin = mdc.origin;
+        let mesh = mdc.tessellate().unwrap();
+        assert!(sphere.zeros.get() > 0, "no exact zeros were sampled");
+        assert_eq!(mdc.origin, origin);
+        mesh.is_closed()?;
+        for v in &mesh.vertices {
+            let p = na::Point3::new(v[0], v[1], v[2]);
+            assert!(sphere.sphere.value(&p).abs() < 0.2);
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn tessellate_with_options() -> Result<(), Box<dyn std::error::Error>> {
+        let torus = Torus::new(1.0, 0.3);
+        let options = TessellationOptions::new(0.1);
+        let simplified = super::ManifoldDualContouring::with_options(&torus, options)
+            .tessellate()
+            .unwrap();
+        let single_threaded = super::ManifoldDualContouring::with_options(&torus, options.threads(1))
+            .tessellate()
+            .unwrap();
+        assert_eq!(simplified, single_threaded);
+        let full = super::ManifoldDualContouring::with_options(&torus, options.simplification(false))
+            .tessellate()
+            .unwrap();
+        full.is_closed()?;
+        assert!(full.faces.len() > simplified.faces.len());
+        let padded = super::ManifoldDualContouring::with_options(&torus, options.padding(2.0))
+            .tessellate()
+            .unwrap();
+        padded.is_closed()?;
+        Ok(())
+    }
+
+    #[test]
+    fn anisotropic_thin_sheet() -> Result<(), Box<dyn std::error::Error>> {
+        let sheet = Cuboid::new(1.0, 1.0, 0.05);
+        let options = TessellationOptions::new(0.2).resolution(na::Vector3::new(0.2, 0.2, 0.02));
+        let mesh = super::ManifoldDualContouring::with_options(&sheet, options)
+            .tessellate()
+            .unwrap();
+        mesh.is_closed()?;
+        for v in &mesh.vertices {
+            let p = na::Point3::new(v[0], v[1], v[2]);
+            assert!(sheet.value(&p).abs() < 0.2, "vertex {v:?} is off the surface");
+            if v[0].abs() < 0.8 && v[1].abs() < 0.8 {
+                // The flat faces are resolved by the fine z resolution.
+                assert!((v[2].abs() - 0.05).abs() < 1e-3, "vertex {v:?} is off the face");
+            }
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn lattice_origin() {
+        let sphere = Sphere::new(na::Point3::new(0.3, 0.2, 0.1), 1.0);
+        let res = na::Vector3::new(0.2, 0.1, 0.3);
+        let lattice = na::Point3::new(0.05, -7.0, 1.0);
+        let options = TessellationOptions::new(0.2).resolution(res).lattice_origin(lattice);
+        let mut mdc = super::ManifoldDualContouring::with_options(&sphere, options);
+        mdc.find_grid();
+        let steps = (mdc.origin - lattice).component_div(&res);
+        for i in 0..3 {
+            assert!((steps[i] - steps[i].round()).abs() < 1e-9, "origin {} is not on the lattice", mdc.origin);
+        }
+    }
+
+    #[test]
+    fn chunked_sphere() -> Result<(), Box<dyn std::error::Error>> {
+        let sphere = Sphere::new(na::Point3::new(0.3, -0.2, 0.1), 1.0);
+        let mut mdc = super::ManifoldDualContouring::new(&sphere, 0.2, 0.0);
+        let mesh = mdc.tessellate_chunked(4).unwrap();
+        mesh.is_closed()?;
+        for v in &mesh.vertices {
+            let p = na::Point3::new(v[0], v[1], v[2]);
+            assert!(sphere.value(&p).abs() < 0.2, "vertex {v:?} too far from the surface");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn projected_vertices() -> Result<(), Box<dyn std::error::Error>> {
+        let torus = Torus::new(1.0, 0.3);
+        let options = TessellationOptions::new(0.1);
+        let max_error = |options| -> Result<f64, Box<dyn std::error::Error>> {
+            let mesh = super::ManifoldDualContouring::with_options(&torus, options)
+                .tessellate()
+                .unwrap();
+            mesh.is_closed()?;
+            Ok(mesh
+                .vertices
+                .iter()
+                .map(|v| torus.value(&na::Point3::from(*v)).abs())
+                .fold(0., f64::max))
+        };
+        let unprojected = max_error(options)?;
+        let projected = max_error(options.project_vertices(0.5))?;
+        assert!(unprojected > 1e-3, "{unprojected}");
+        assert!(projected < 1e-6, "{projected}");
+        Ok(())
+    }
+
+    #[test]
+    fn vertex_attributes() {
+        let sphere = Sphere::new(na::Point3::new(0.3, -0.2, 0.1), 1.0);
+        let height = |p: &na::Point3<f64>| [p.z as f32, 1.];
+        let mut mdc = super::ManifoldDualContouring::new(&sphere, 0.2, 0.1)
+            .with_vertex_attributes(&height);
+        for mesh in [mdc.tessellate().unwrap(), mdc.tessellate_chunked(4).unwrap()] {
+            assert_eq!(mesh.attribute_stride, 2);
+            for (i, v) in mesh.vertices.iter().enumerate() {
+                assert_eq!(mesh.attributes(i), &[v[2] as f32, 1.]);
+            }
+        }
+    }
+
+    // Sink that checks that triangles only reference vertices added before.
+    #[derive(Default)]
+    struct CheckingSink(crate::Mesh<f64>);
+
+    impl MeshSink<f64> for CheckingSink {
+        fn add_vertex(&mut self, v: [f64; 3]) -> usize {
+            self.0.add_vertex(v)
+        }
+        fn add_triangle(&mut self, f: [usize; 3]) {
+            assert!(f.iter().all(|&i| i < self.0.vertices.len()), "face {f:?} references future vertex");
+            self.0.add_triangle(f)
+        }
+    }
+
+    #[test]
+    fn chunked_torus_with_simplification() -> Result<(), Box<dyn std::error::Error>> {
+        let torus = Torus::new(1.0, 0.3);
+        let mut mdc = super::ManifoldDualContouring::new(&torus, 0.1, 0.1);
+        let mut sink = CheckingSink::default();
+        mdc.tessellate_chunked_into(8, &mut sink);
+        assert!(sink.0.faces.len() > 1000);
+        Ok(sink.0.is_closed()?)
+    }
+
+    #[test]
+    fn torus_lods() -> Result<(), Box<dyn std::error::Error>> {
+        use super::LevelOfDetail;
+        let torus = Torus::new(1.0, 0.3);
+        let mut mdc = super::ManifoldDualContouring::new(&torus, 0.05, 0.);
+        let lods = mdc.tessellate_lods(&[
+            LevelOfDetail::Depth(0),
+            LevelOfDetail::RelativeError(0.01),
+            LevelOfDetail::RelativeError(0.05),
+            LevelOfDetail::Depth(2),
+        ]);
+        assert_eq!(lods.len(), 4);
+        let reference = super::ManifoldDualContouring::new(&torus, 0.05, 0.01)
+            .tessellate()
+            .unwrap();
+        assert_eq!(lods[1].faces, reference.faces);
+        for pair in lods[..3].windows(2) {
+            assert!(pair[0].faces.len() >= pair[1].faces.len());
+        }
+        assert!(lods[0].faces.len() > lods[2].faces.len());
+        assert!(lods[3].faces.len() < lods[0].faces.len());
+        for mesh in &lods[..2] {
+            mesh.is_closed()?;
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn torus_face_budget() -> Result<(), Box<dyn std::error::Error>> {
+        let torus = Torus::new(1.0, 0.3);
+        let full = super::ManifoldDualContouring::new(&torus, 0.05, 0.)
+            .tessellate()
+            .unwrap();
+        let budget = full.faces.len() / 4;
+        let options = TessellationOptions::new(0.05).face_budget(budget);
+        let mut mdc = super::ManifoldDualContouring::with_options(&torus, options);
+        let mesh = mdc.tessellate().unwrap();
+        assert_eq!(mdc.face_budget_met(), Some(true));
+        assert!(mesh.faces.len() <= budget);
+        assert!(mesh.faces.len() > budget * 9 / 10, "{} of {budget}", mesh.faces.len());
+        mesh.is_closed()?;
+
+        // The manifold check keeps the sphere from collapsing to a handful of faces.
+        let sphere = Sphere::unit();
+        let options = TessellationOptions::new(0.05).face_budget(10);
+        let mut mdc = super::ManifoldDualContouring::with_options(&sphere, options);
+        let mesh = mdc.tessellate().unwrap();
+        assert_eq!(mdc.face_budget_met(), Some(false));
+        assert!(mesh.faces.len() > 10);
+        mesh.is_closed()?;
+        assert_eq!(super::ManifoldDualContouring::new(&torus, 0.05, 0.).face_budget_met(), None);
+        Ok(())
+    }
+
+    // Torus with a sphere added at (1, 0.3, 0).
+    struct BumpedTorus {
+        torus: Torus,
+        bump: Sphere,
+    }
+    impl super::ImplicitFunction<f64> for BumpedTorus {
+        fn value(&self, p: &na::Point3<f64>) -> f64 {
+            self.torus.value(p).min(self.bump.value(p))
+        }
+        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+            if self.torus.value(p) < self.bump.value(p) {
+                self.torus.normal(p)
+            } else {
+                self.bump.normal(p)
+            }
+        }
+    }
+
+    #[test]
+    fn retessellate_region() -> Result<(), Box<dyn std::error::Error>> {
+        let torus = Torus::new(1.0, 0.3);
+        let mut mdc = super::ManifoldDualContouring::new(&torus, 0.05, 0.05);
+        let mut mesh = mdc.tessellate().unwrap();
+        let before = mesh.clone();
+
+        let bumped = BumpedTorus {
+            torus: Torus::new(1.0, 0.3),
+            bump: Sphere::new(na::Point3::new(1., 0.3, 0.), 0.15),
+        };
+        let bbox = bbox::BoundingBox::new(
+            &na::Point3::new(0.85, 0.15, -0.15),
+            &na::Point3::new(1.15, 0.45, 0.15),
+        );
+        mdc.retessellate_region(&bumped, &bbox, &mut mesh)?;
+        mesh.is_closed()?;
+        assert_ne!(mesh, before);
+
+        let expected = super::ManifoldDualContouring::new(&bumped, 0.05, 0.05)
+            .tessellate()
+            .unwrap();
+        assert_eq!(triangles(&mesh), triangles(&expected));
+        assert_eq!(mesh.vertices.len(), expected.vertices.len());
+        // Vertices far from the edit keep their index.
+        let kept = before
+            .vertices
+            .iter()
+            .zip(mesh.vertices.iter())
+            .filter(|(a, b)| a == b)
+            .count();
+        assert!(kept > before.vertices.len() / 2, "{} of {}", kept, before.vertices.len());
+
+        // Undo the edit.
+        mdc.retessellate_region(&torus, &bbox, &mut mesh)?;
+        mesh.is_closed()?;
+        assert_eq!(triangles(&mesh), triangles(&before));
+
+        let mut chunked = super::ManifoldDualContouring::new(&torus, 0.05, 0.05);
+        assert_eq!(
+            chunked.retessellate_region(&bumped, &bbox, &mut mesh),
+            Err(super::RetessellateError::NotTessellated)
+        );
+        Ok(())
+    }
+
+    // The triangles of mesh by vertex position, starting at their smallest vertex. Merged vertices
+    // may differ in the last bits, depending on the order their children were added in.
+    fn triangles(mesh: &crate::Mesh<f64>) -> Vec<[[i64; 3]; 3]> {
+        let mut triangles: Vec<_> = mesh
+            .faces
+            .iter()
+            .map(|&f| {
+                let mut t = f.map(|v| mesh.vertices[v].map(|c| (c * 1e9).round() as i64));
+                let first = (0..3).min_by_key(|&i| t[i]).unwrap();
+                t.rotate_left(first);
+                t
+            })
+            .collect();
+        triangles.sort_unstable();
+        triangles
+    }
+
+    // The values of the sphere overestimate the distance, so without intervals or the Lipschitz
+    // constant most of the surface would be culled.
+    fn tessellate_scaled_sphere(interval: bool) -> Result<(), Box<dyn std::error::Error>> {
+        let sphere = ScaledSphere {
+            scale: 100.,
+            interval,
+        };
+        let mesh = super::ManifoldDualContouring::new(&sphere, 0.1, 0.)
+            .tessellate()
+            .unwrap();
+        mesh.is_closed()?;
+        assert!(mesh.faces.len() > 3000);
+        for v in &mesh.vertices {
+            assert!((na::Vector3::from(*v).norm() - 1.).abs() < 0.1, "{v:?}");
+        }
+        Ok(())
+    }
+
+    #[test]
+    fn interval_culling() -> Result<(), Box<dyn std::error::Error>> {
+        tessellate_scaled_sphere(true)
+    }
+
+    #[test]
+    fn lipschitz_culling() -> Result<(), Box<dyn std::error::Error>> {
+        tessellate_scaled_sphere(false)
+    }
+
+    #[test]
+    fn periodic_gyroid() {
+        use std::collections::HashMap;
+        let two_pi = 2. * std::f64::consts::PI;
+        let period = bbox::BoundingBox::new(
+            &na::Point3::new(0., 0., 0.),
+            &na::Point3::new(two_pi, two_pi, two_pi),
+        );
+        let mut mdc = super::ManifoldDualContouring::new(&Gyroid, 0.3, 0.);
+        let tile = mdc.tessellate_periodic(&period, [1, 1, 1]);
+        let mut mdc = super::ManifoldDualContouring::new(&Gyroid, 0.3, 0.);
+        let tiling = mdc.tessellate_periodic(&period, [2, 2, 2]);
+        assert!(tile.faces.len() > 1000);
+        assert_eq!(tiling.faces.len(), 8 * tile.faces.len());
+        assert!(tiling.vertices.len() < 8 * tile.vertices.len());
+
+        // Every edge is used once in each direction, except on the outer boundary of the tiling.
+        let mut edges = HashMap::new();
+        for f in &tiling.faces {
+            for i in 0..3 {
+                assert!(edges.insert((f[i], f[(i + 1) % 3]), ()).is_none());
+            }
+        }
+        let res = two_pi / (two_pi / 0.3).round();
+        for &(a, b) in edges.keys() {
+            if !edges.contains_key(&(b, a)) {
+                let on_outer_boundary = |v: [f64; 3]| {
+                    v.iter().any(|&c| c < 1.5 * res || c > 2. * two_pi - 1.5 * res)
+                };
+                assert!(on_outer_boundary(tiling.vertices[a]));
+                assert!(on_outer_boundary(tiling.vertices[b]));
+            }
+        }
+    }
+
+    #[test]
+    #[should_panic(expected = "use a coarser resolution")]
+    fn grid_too_large() {
+        let sphere = Sphere::new(na::Point3::origin(), 1.0);
+        let mut mdc = super::ManifoldDualContouring::new(&sphere, 0.1, 0.);
+        mdc.set_dim([10, 10, super::GRID_LIMIT / 2]);
+    }
+
+    #[test]
+    fn periodic_deterministic() {
+        let two_pi = 2. * std::f64::consts::PI;
+        let period = bbox::BoundingBox::new(
+            &na::Point3::new(0., 0., 0.),
+            &na::Point3::new(two_pi, two_pi, two_pi),
+        );
+        let tessellate = || {
+            let mesh = super::ManifoldDualContouring::new(&Gyroid, 0.5, 0.)
+                .tessellate_periodic(&period, [2, 2, 2]);
+            let bits: Vec<[u64; 3]> = mesh.vertices.iter().map(|v| v.map(f64::to_bits)).collect();
+            (bits, mesh.faces)
+        };
+        // Vertices and faces must be bit-identical and in the same order in every run, which
+        // holds only if the representatives of the shared vertices do not depend on the iteration
+        // order of hash maps.
+        let first = tessellate();
+        for _ in 0..3 {
+            assert!(tessellate() == first);
+        }
+    }
+
+    #[test]
+    fn clipped_sphere() -> Result<(), Box<dyn std::error::Error>> {
+        let sphere = Sphere::new(na::Point3::origin(), 1.0);
+        let clip = crate::ClipRegion::HalfSpace {
+            point: na::Point3::new(0., 0., 0.3),
+            normal: na::Vector3::new(0., 0., 1.),
+        };
+        let options = TessellationOptions::new(0.1).clip(clip);
+        let mesh = super::ManifoldDualContouring::with_options(&sphere, options)
+            .tessellate()
+            .unwrap();
+        mesh.is_closed()?;
+        assert_eq!(mesh.face_tags.len(), mesh.faces.len());
+        let mut caps = 0;
+        for (f, &tag) in mesh.faces.iter().zip(mesh.face_tags.iter()) {
+            let v = f.map(|i| na::Point3::from(mesh.vertices[i]));
+            if tag == crate::CAP_FACE_TAG {
+                caps += 1;
+                assert!(v.iter().all(|v| (v.z - 0.3).abs() < 0.01), "{v:?}");
+            } else {
+                assert_eq!(tag, 0);
+                assert!(v.iter().all(|v| (v.coords.norm() - 1.).abs() < 0.1 && v.z < 0.31));
+            }
+        }
+        assert!(caps > 50);
+        Ok(())
+    }
+
+    // Union of spheres, tagged with the index of the closest one.
+    struct TaggedUnion(Vec<Sphere>);
+
+    impl TaggedUnion {
+        fn closest(&self, p: &na::Point3<f64>) -> usize {
+            (0..self.0.len())
+                .min_by(|&a, &b| self.0[a].value(p).total_cmp(&self.0[b].value(p)))
+                .unwrap()
+        }
+    }
+
+    impl ImplicitFunction<f64> for TaggedUnion {
+        fn value(&self, p: &na::Point3<f64>) -> f64 {
+            self.0[self.closest(p)].value(p)
+        }
+        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+            self.0[self.closest(p)].normal(p)
+        }
+        fn tag(&self, p: &na::Point3<f64>) -> Option<u32> {
+            Some(self.closest(p) as u32)
+        }
+    }
+
+    #[test]
+    fn face_provenance() -> Result<(), Box<dyn std::error::Error>> {
+        let union = TaggedUnion(vec![
+            Sphere::new(na::Point3::new(-0.5, 0., 0.), 0.8),
+            Sphere::new(na::Point3::new(0.5, 0., 0.), 0.8),
+        ]);
+        let mesh = super::ManifoldDualContouring::new(&union, 0.1, 0.1)
+            .tessellate()
+            .unwrap();
+        mesh.is_closed()?;
+        assert_eq!(mesh.face_tags.len(), mesh.faces.len());
+        for (f, &tag) in mesh.faces.iter().zip(mesh.face_tags.iter()) {
+            let x = f.iter().map(|&i| mesh.vertices[i][0]).sum::<f64>() / 3.;
+            if x.abs() > 0.2 {
+                assert_eq!(tag, union.closest(&na::Point3::new(x, 0., 0.)) as u32);
+            }
+        }
+        let untagged = super::ManifoldDualContouring::new(&union.0[0], 0.1, 0.1)
+            .tessellate()
+            .unwrap();
+        assert!(untagged.face_tags.is_empty());
+        Ok(())
+    }
+
     #[test]
     #[ignore]
     // This test exposes https://github.com/hmeyer/tessellation/issues/7
@@ -1606,7 +3468,7 @@ mod tests {
     #[test]
     fn find_bounds_sphere_at_origin() {
         let sphere = Sphere::new(na::Point3::origin(), 1.0);
-        let bbox = super::find_bounds(&sphere as &dyn super::ImplicitFunction<f64>, 0.2);
+        let bbox = super::find_bounds(&sphere as &dyn super::ImplicitFunction<f64>, &TessellationOptions::new(0.2));
         assert!(bbox.min.x <= -1.0, "min.x = {:.3}", bbox.min.x);
         assert!(bbox.min.y <= -1.0, "min.y = {:.3}", bbox.min.y);
         assert!(bbox.min.z <= -1.0, "min.z = {:.3}", bbox.min.z);
@@ -1619,7 +3481,7 @@ mod tests {
     fn find_bounds_sphere_offset() {
         // Sphere at (5, 3, −2) radius 1; surface extents [4,6]×[2,4]×[−3,−1].
         let sphere = Sphere::new(na::Point3::new(5.0, 3.0, -2.0), 1.0);
-        let bbox = super::find_bounds(&sphere as &dyn super::ImplicitFunction<f64>, 0.2);
+        let bbox = super::find_bounds(&sphere as &dyn super::ImplicitFunction<f64>, &TessellationOptions::new(0.2));
         assert!(bbox.min.x <= 4.0,  "min.x = {:.3}", bbox.min.x);
         assert!(bbox.min.y <= 2.0,  "min.y = {:.3}", bbox.min.y);
         assert!(bbox.min.z <= -3.0, "min.z = {:.3}", bbox.min.z);
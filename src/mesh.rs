@@ -1,10 +1,23 @@
 use nalgebra as na;
 use nalgebra::RealField;
+use num_traits::Float;
 #[cfg(test)]
 use std::error::Error;
 #[cfg(test)]
 use std::fmt;
+use std::collections::HashMap;
 use std::fmt::Debug;
+use std::io;
+
+// Position of a vertex as a point.
+pub(crate) fn point<S: RealField + Copy>(v: &[S; 3]) -> na::Point3<S> {
+    na::Point3::new(v[0], v[1], v[2])
+}
+
+// Error for malformed mesh files.
+pub(crate) fn invalid_data(msg: impl Into<String>) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, msg.into())
+}
 
 #[cfg(test)]
 #[derive(Debug, PartialEq)]
@@ -29,6 +42,210 @@ pub struct Mesh<S> {
     pub vertices: Vec<[S; 3]>,
     /// The list of triangles as indexes into vertices.
     pub faces: Vec<[usize; 3]>,
+    /// A tag for each face, or empty if no face was tagged.
+    pub face_tags: Vec<u32>,
+    /// Attributes of all vertices, e.g. colours, `attribute_stride` per vertex, or empty if no
+    /// vertex has attributes. Use [`attributes`](Self::attributes) to get those of a vertex.
+    pub vertex_attributes: Vec<f32>,
+    /// Number of attributes per vertex, or 0 if no vertex has attributes.
+    pub attribute_stride: usize,
+}
+
+/// Receiver for the vertices and triangles of a mesh while it is generated.
+///
+/// Implement this to process a mesh without ever holding all of it in memory.
+pub trait MeshSink<S> {
+    /// Add a vertex and return its index. Indexes have to be consecutive, starting at 0.
+    fn add_vertex(&mut self, v: [S; 3]) -> usize;
+    /// Add a triangle as indexes of previously added vertices.
+    fn add_triangle(&mut self, f: [usize; 3]);
+    /// Add a triangle with a tag, e.g. [`CAP_FACE_TAG`](crate::CAP_FACE_TAG). Sinks that do not
+    /// store tags can rely on the default, which drops the tag.
+    fn add_tagged_triangle(&mut self, f: [usize; 3], _tag: u32) {
+        self.add_triangle(f);
+    }
+    /// Add a vertex with attributes, e.g. sampled with
+    /// [`with_vertex_attributes`](crate::ManifoldDualContouring::with_vertex_attributes). Sinks
+    /// that do not store attributes can rely on the default, which drops them.
+    fn add_vertex_with_attributes(&mut self, v: [S; 3], _attributes: &[f32]) -> usize {
+        self.add_vertex(v)
+    }
+}
+
+impl<S> MeshSink<S> for Mesh<S> {
+    fn add_vertex(&mut self, v: [S; 3]) -> usize {
+        let len = self.vertex_attributes.len();
+        self.vertex_attributes.resize(len + self.attribute_stride, 0.);
+        self.vertices.push(v);
+        self.vertices.len() - 1
+    }
+    fn add_triangle(&mut self, f: [usize; 3]) {
+        self.faces.push(f);
+        if !self.face_tags.is_empty() {
+            self.face_tags.push(0);
+        }
+    }
+    fn add_tagged_triangle(&mut self, f: [usize; 3], tag: u32) {
+        // Faces added before the first tag are tagged with 0.
+        self.face_tags.resize(self.faces.len(), 0);
+        self.faces.push(f);
+        self.face_tags.push(tag);
+    }
+    fn add_vertex_with_attributes(&mut self, v: [S; 3], attributes: &[f32]) -> usize {
+        if self.attribute_stride == 0 {
+            // Vertices added before the first attributes get zeros.
+            self.attribute_stride = attributes.len();
+            self.vertex_attributes = vec![0.; self.vertices.len() * attributes.len()];
+        }
+        assert_eq!(
+            attributes.len(),
+            self.attribute_stride,
+            "all vertices must have the same number of attributes"
+        );
+        self.vertices.push(v);
+        self.vertex_attributes.extend_from_slice(attributes);
+        self.vertices.len() - 1
+    }
+}
+
+impl<S> Default for Mesh<S> {
+    fn default() -> Self {
+        Mesh {
+            vertices: Vec::new(),
+            faces: Vec::new(),
+            face_tags: Vec::new(),
+            vertex_attributes: Vec::new(),
+            attribute_stride: 0,
+        }
+    }
+}
+
+impl<S> Mesh<S> {
+    /// Return the attributes of vertex `v`, or an empty slice if the mesh has no attributes.
+    pub fn attributes(&self, v: usize) -> &[f32] {
+        let stride = self.attribute_stride;
+        &self.vertex_attributes[v * stride..(v + 1) * stride]
+    }
+    /// Return the attributes of vertex `v` for modification.
+    pub fn attributes_mut(&mut self, v: usize) -> &mut [f32] {
+        let stride = self.attribute_stride;
+        &mut self.vertex_attributes[v * stride..(v + 1) * stride]
+    }
+}
+
+impl<S: Copy> Mesh<S> {
+    // Drop the faces marked in removed and all vertices no face refers to anymore. Face tags and
+    // vertex attributes are kept for the remaining faces and vertices.
+    pub(crate) fn remove_faces(&mut self, removed: &[bool]) {
+        let mut used = vec![false; self.vertices.len()];
+        let mut faces = Vec::new();
+        let mut face_tags = Vec::new();
+        for (i, f) in self.faces.iter().enumerate() {
+            if !removed[i] {
+                faces.push(*f);
+                if let Some(&tag) = self.face_tags.get(i) {
+                    face_tags.push(tag);
+                }
+                f.iter().for_each(|&v| used[v] = true);
+            }
+        }
+        let mut index = vec![usize::MAX; self.vertices.len()];
+        let mut vertices = Vec::new();
+        let mut vertex_attributes = Vec::new();
+        for (i, v) in self.vertices.iter().enumerate() {
+            if used[i] {
+                index[i] = vertices.len();
+                vertices.push(*v);
+                vertex_attributes.extend_from_slice(self.attributes(i));
+            }
+        }
+        self.faces = faces.iter().map(|f| f.map(|v| index[v])).collect();
+        self.vertices = vertices;
+        self.face_tags = face_tags;
+        self.vertex_attributes = vertex_attributes;
+    }
+    // Add a convex polygon as a fan of triangles, checking its vertex indexes.
+    pub(crate) fn add_polygon(&mut self, polygon: &[usize]) -> io::Result<()> {
+        if polygon.len() < 3 {
+            return Err(invalid_data("face with less than 3 vertices"));
+        }
+        if let Some(&v) = polygon.iter().find(|&&v| v >= self.vertices.len()) {
+            return Err(invalid_data(format!("face refers to missing vertex {}", v)));
+        }
+        for i in 1..polygon.len() - 1 {
+            self.faces.push([polygon[0], polygon[i], polygon[i + 1]]);
+        }
+        Ok(())
+    }
+}
+
+impl<S: Float> Mesh<S> {
+    // Replace self by other, but keep the index of every vertex of self that also exists in other.
+    // New vertices fill the slots of removed ones. Remaining slots are filled by moving the
+    // vertices from the end of the list.
+    pub(crate) fn replace_reusing_indices(&mut self, other: Mesh<S>) {
+        let key = |v: &[S; 3]| v.map(|c| c.integer_decode());
+        let mut old_index = HashMap::new();
+        for (i, v) in self.vertices.iter().enumerate().rev() {
+            old_index.entry(key(v)).or_insert_with(Vec::new).push(i);
+        }
+        let mut used = vec![false; self.vertices.len()];
+        let mut index: Vec<Option<usize>> = other
+            .vertices
+            .iter()
+            .map(|v| {
+                let i = old_index.get_mut(&key(v))?.pop()?;
+                used[i] = true;
+                Some(i)
+            })
+            .collect();
+        let mut free = (0..self.vertices.len()).filter(|&i| !used[i]);
+        for (v, i) in other.vertices.iter().zip(index.iter_mut()) {
+            if i.is_none() {
+                *i = Some(match free.next() {
+                    Some(slot) => {
+                        self.vertices[slot] = *v;
+                        slot
+                    }
+                    None => {
+                        self.vertices.push(*v);
+                        self.vertices.len() - 1
+                    }
+                });
+            }
+        }
+        let mut index: Vec<usize> = index.into_iter().map(Option::unwrap).collect();
+        // Close the remaining gaps with the last vertices.
+        let mut holes: Vec<usize> = free.collect();
+        let mut new_index: Vec<usize> = (0..self.vertices.len()).collect();
+        let mut len = self.vertices.len();
+        let mut next_hole = 0;
+        while next_hole < holes.len() {
+            if holes.last() == Some(&(len - 1)) {
+                // Unused slots at the end are simply dropped.
+                holes.pop();
+                len -= 1;
+                continue;
+            }
+            let hole = holes[next_hole];
+            next_hole += 1;
+            len -= 1;
+            self.vertices[hole] = self.vertices[len];
+            new_index[len] = hole;
+        }
+        self.vertices.truncate(len);
+        for i in &mut index {
+            *i = new_index[*i];
+        }
+        self.faces = other.faces.iter().map(|f| f.map(|i| index[i])).collect();
+        // Every remaining slot holds a vertex of other, so the attributes are just permuted.
+        self.attribute_stride = other.attribute_stride;
+        self.vertex_attributes = vec![0.; self.vertices.len() * other.attribute_stride];
+        for (j, &i) in index.iter().enumerate() {
+            self.attributes_mut(i).copy_from_slice(other.attributes(j));
+        }
+        self.face_tags = other.face_tags;
+    }
 }
 
 impl<S: RealField + Copy + Debug> Mesh<S> {
@@ -120,10 +337,47 @@ mod test {
         let m = Mesh {
             vertices: vec![[0., 0., 0.], [1., 0., 0.], [0., 1., 0.]],
             faces: vec![[0, 1, 2]],
+            face_tags: Vec::new(),
+            vertex_attributes: Vec::new(),
+            attribute_stride: 0,
         };
         assert!(f32slice_eq(&m.normal32(0), &[0., 0., 1.]));
         assert!(f32slice_eq(&m.vertex32(0), &[0., 0., 0.]));
         assert!(f32slice_eq(&m.vertex32(1), &[1., 0., 0.]));
         assert!(f32slice_eq(&m.vertex32(2), &[0., 1., 0.]));
     }
+
+    #[test]
+    fn replace_reusing_indices() {
+        let mut m = Mesh {
+            vertices: vec![[0., 0., 0.], [1., 0., 0.], [0., 1., 0.], [2., 0., 0.], [3., 0., 0.]],
+            faces: vec![[0, 1, 2], [1, 3, 4]],
+            face_tags: Vec::new(),
+            vertex_attributes: Vec::new(),
+            attribute_stride: 0,
+        };
+        // [1, 0, 0] and [3, 0, 0] are removed, [0, 2, 0] is new.
+        let other = Mesh {
+            vertices: vec![[0., 2., 0.], [2., 0., 0.], [0., 1., 0.], [0., 0., 0.]],
+            faces: vec![[3, 0, 2], [0, 1, 2]],
+            face_tags: Vec::new(),
+            vertex_attributes: Vec::new(),
+            attribute_stride: 0,
+        };
+        m.replace_reusing_indices(other);
+        assert_eq!(m.vertices, vec![[0., 0., 0.], [0., 2., 0.], [0., 1., 0.], [2., 0., 0.]]);
+        assert_eq!(m.faces, vec![[0, 1, 2], [1, 3, 2]]);
+
+        // Without new vertices, the last vertex moves into the gap.
+        let other = Mesh {
+            vertices: vec![[2., 0., 0.], [0., 1., 0.], [0., 2., 0.]],
+            faces: vec![[0, 1, 2]],
+            face_tags: Vec::new(),
+            vertex_attributes: Vec::new(),
+            attribute_stride: 0,
+        };
+        m.replace_reusing_indices(other);
+        assert_eq!(m.vertices, vec![[2., 0., 0.], [0., 2., 0.], [0., 1., 0.]]);
+        assert_eq!(m.faces, vec![[0, 2, 1]]);
+    }
 }
@@ -0,0 +1,440 @@
+use crate::{
+    bitset::BitSet,
+    cell_configs::CELL_CONFIGS,
+    manifold_dual_contouring::{push_quad, quad_cells, Edge, EdgeIndex, ManifoldDualContouring},
+    mesh::{Mesh, MeshSink},
+    plane::Plane,
+    qef::{Qef, EPSILON},
+    sparse_grid::SparseGrid,
+    vertex_index::{offset, Index},
+    AsUSize, ImplicitFunction, RealField, TessellationOptions,
+};
+use bbox::BoundingBox;
+use nalgebra as na;
+use num_traits::Float;
+use std::collections::HashMap;
+
+/// Multi-material extractor.
+///
+/// Each point belongs to the material with the smallest negative value, or to no material, if
+/// all values are positive. The interface between two materials is where their values are equal.
+/// [`tessellate`](Self::tessellate) returns a closed mesh per material. Vertices on interfaces
+/// are computed once and are bit-identical in the meshes of all materials sharing them.
+pub struct MultiMaterial<'a, S: RealField> {
+    functions: Vec<&'a dyn ImplicitFunction<S>>,
+    options: TessellationOptions<S>,
+}
+
+impl<'a, S: From<f32> + RealField + Float + AsUSize> MultiMaterial<'a, S> {
+    /// Constructor
+    /// functions: one function per material
+    /// res: resolution
+    pub fn new(functions: &[&'a dyn ImplicitFunction<S>], res: S) -> MultiMaterial<'a, S> {
+        Self::with_options(functions, TessellationOptions::new(res))
+    }
+    /// Constructor
+    /// functions: one function per material
+    /// options: resolution and all other settings.
+    ///
+    /// Only the resolution, `lattice_origin`, `precision`, `padding`, `max_march_distance` and
+    /// `max_expand_steps` are used. There is no simplification, so `relative_error`,
+    /// `simplification` and `manifold_check` are ignored, and sampling runs on a single thread
+    /// regardless of `threads`. [`ImplicitFunction::interval`] of the functions is not used.
+    /// Panics, if `face_budget`, `adaptive_sampling`, `clip` or `project_vertices` is set, as
+    /// they are not supported.
+    pub fn with_options(
+        functions: &[&'a dyn ImplicitFunction<S>],
+        options: TessellationOptions<S>,
+    ) -> MultiMaterial<'a, S> {
+        assert!(!functions.is_empty(), "at least one material is required");
+        assert!(
+            options.face_budget.is_none()
+                && options.adaptive.is_none()
+                && options.clip.is_none()
+                && options.projection.is_none(),
+            "face_budget, adaptive_sampling, clip and project_vertices are not supported"
+        );
+        MultiMaterial {
+            functions: functions.to_vec(),
+            options,
+        }
+    }
+    /// Tessellate all materials and return a mesh per material, in the order of the functions.
+    /// The faces of each mesh are tagged with the index of its material.
+    pub fn tessellate(&self) -> Vec<Mesh<S>> {
+        let union = Union(&self.functions);
+        let mut mdc = ManifoldDualContouring::with_options(&union, self.options);
+        mdc.find_grid();
+        let mut grid = LabelGrid {
+            functions: &self.functions,
+            origin: mdc.origin,
+            res: mdc.res,
+            dim: mdc.dim,
+            precision: self.options.precision,
+            lipschitz: union.lipschitz(),
+            labels: SparseGrid::new(),
+            crossings: SparseGrid::new(),
+        };
+        let maxdim = grid.dim.iter().copied().max().unwrap();
+        grid.sample([0, 0, 0], maxdim.next_power_of_two());
+        grid.find_crossings();
+        grid.generate_meshes()
+    }
+}
+
+// The union of all materials. Only used to find the bounds.
+struct Union<'b, 'a, S: RealField>(&'b [&'a dyn ImplicitFunction<S>]);
+
+impl<S: RealField + Float> ImplicitFunction<S> for Union<'_, '_, S> {
+    fn value(&self, p: &na::Point3<S>) -> S {
+        self.0.iter().map(|f| f.value(p)).fold(Float::infinity(), Float::min)
+    }
+    fn normal(&self, p: &na::Point3<S>) -> na::Vector3<S> {
+        let closest = self
+            .0
+            .iter()
+            .min_by(|a, b| a.value(p).total_cmp(&b.value(p)))
+            .unwrap();
+        closest.normal(p)
+    }
+    fn lipschitz(&self) -> S {
+        self.0.iter().map(|f| f.lipschitz()).fold(S::one(), Float::max)
+    }
+}
+
+// Grid of material labels. The label of a point is the index of its material, or
+// functions.len() for no material.
+struct LabelGrid<'b, 'a, S: RealField> {
+    functions: &'b [&'a dyn ImplicitFunction<S>],
+    origin: na::Point3<S>,
+    res: na::Vector3<S>,
+    dim: [usize; 3],
+    precision: S,
+    // Lipschitz constant of the union of all functions.
+    lipschitz: S,
+    labels: SparseGrid<Index, usize>,
+    // Position and normal of the interface crossing each edge with different labels.
+    crossings: SparseGrid<EdgeIndex, Plane<S>>,
+}
+
+impl<S: From<f32> + RealField + Float + AsUSize> LabelGrid<'_, '_, S> {
+    fn void(&self) -> usize {
+        self.functions.len()
+    }
+    fn grid_point(&self, idx: Index) -> na::Point3<S> {
+        self.origin
+            + na::Vector3::new(
+                From::from(idx[0] as f32),
+                From::from(idx[1] as f32),
+                From::from(idx[2] as f32),
+            )
+            .component_mul(&self.res)
+    }
+    // Value of material label at p. No material is treated as a function that is 0 everywhere.
+    fn value(&self, label: usize, p: &na::Point3<S>) -> S {
+        match self.functions.get(label) {
+            Some(f) => f.value(p),
+            None => From::from(0f32),
+        }
+    }
+    fn normal(&self, label: usize, p: &na::Point3<S>) -> na::Vector3<S> {
+        match self.functions.get(label) {
+            Some(f) => f.normal(p),
+            None => na::Vector3::zeros(),
+        }
+    }
+    // Returns the label at p and by how much the second smallest value exceeds the smallest.
+    fn label(&self, p: &na::Point3<S>) -> (usize, S) {
+        let mut best = (self.void(), From::from(0f32));
+        let mut second = Float::infinity();
+        for (i, f) in self.functions.iter().enumerate() {
+            let v = f.value(p);
+            if v < best.1 {
+                second = best.1;
+                best = (i, v);
+            } else if v < second {
+                second = v;
+            }
+        }
+        (best.0, second - best.1)
+    }
+    // Sample the sub-cube [idx, idx + size). If all values change by less than half the margin
+    // between the two smallest ones within the sub-cube, the label is the same everywhere and
+    // only the corner is stored.
+    fn sample(&mut self, idx: Index, size: usize) {
+        if (0..3).any(|i| idx[i] > self.dim[i]) {
+            return;
+        }
+        let p = self.grid_point(idx);
+        let (label, margin) = self.label(&p);
+        let two: S = From::from(2f32);
+        // Pad by two cells, so that the cells around all crossing edges are sampled completely.
+        let size_s: S = From::from((size + 2) as f32);
+        if size == 1 || margin > two * self.lipschitz * (self.res * size_s).norm() {
+            self.labels.insert(idx, label);
+            return;
+        }
+        let size = size / 2;
+        for z in 0..2 {
+            for y in 0..2 {
+                for x in 0..2 {
+                    self.sample(offset(idx, [x * size, y * size, z * size]), size);
+                }
+            }
+        }
+    }
+    fn find_crossings(&mut self) {
+        let points: Vec<(Index, usize)> = self.labels.iter().map(|(i, &l)| (i, l)).collect();
+        for (idx, label) in points {
+            for edge in [Edge::A, Edge::B, Edge::C] {
+                let mut adjacent = idx;
+                adjacent[edge as usize] += 1;
+                match self.labels.get(&adjacent) {
+                    Some(&adjacent_label) if adjacent_label != label => {
+                        let plane = self.find_interface(
+                            self.grid_point(idx),
+                            label,
+                            self.grid_point(adjacent),
+                            adjacent_label,
+                        );
+                        self.crossings.insert(EdgeIndex { edge, index: idx }, plane);
+                    }
+                    _ => {}
+                }
+            }
+        }
+    }
+    // Bisect the edge from a (label la) to b (label lb) for the point where the values of both
+    // labels are equal.
+    fn find_interface(
+        &self,
+        a: na::Point3<S>,
+        la: usize,
+        b: na::Point3<S>,
+        lb: usize,
+    ) -> Plane<S> {
+        let half: S = From::from(0.5f32);
+        let zero: S = From::from(0f32);
+        let (mut lo, mut hi) = (a, b);
+        let precision = self.precision * (b - a).norm();
+        while (hi - lo).norm() > precision {
+            let mid = na::center(&lo, &hi);
+            if self.value(la, &mid) - self.value(lb, &mid) < zero {
+                lo = mid;
+            } else {
+                hi = mid;
+            }
+        }
+        let p = lo + (hi - lo) * half;
+        let n = (self.normal(la, &p) - self.normal(lb, &p))
+            .try_normalize(From::from(EPSILON))
+            .unwrap_or_else(|| self.normal(la, &p));
+        Plane { p, n }
+    }
+    // Returns the labels of the eight corners of cell idx.
+    fn cell_labels(&self, idx: Index) -> [usize; 8] {
+        std::array::from_fn(|corner| {
+            let corner_idx = offset(idx, [corner & 1, corner >> 1 & 1, corner >> 2]);
+            *self
+                .labels
+                .get(&corner_idx)
+                .unwrap_or_else(|| panic!("did not find labels[{:?}]", corner_idx))
+        })
+    }
+    // Returns the edge sets of the vertices of cell idx. For each material, CELL_CONFIGS gives the
+    // edge sets of the manifold vertices separating it from all other labels. Edge sets of two
+    // materials that share an edge on their interface belong to the same vertex, so that it is
+    // shared by both materials. No material has no edge sets of its own: an edge between a
+    // material and no material only uses the sets of the material, so that separate sheets of it
+    // are not joined through the empty space between them.
+    fn cell_edge_sets(&self, idx: Index) -> Vec<BitSet> {
+        let labels = self.cell_labels(idx);
+        let mut edge_sets: Vec<BitSet> = Vec::new();
+        for (i, &label) in labels.iter().enumerate() {
+            if label == self.void() || labels[..i].contains(&label) {
+                continue;
+            }
+            let mut corners = BitSet::zero();
+            for (corner, &l) in labels.iter().enumerate() {
+                if l == label {
+                    corners.set(corner);
+                }
+            }
+            for &label_set in CELL_CONFIGS[corners.as_u32() as usize] {
+                let mut merged = label_set;
+                edge_sets.retain(|&set| {
+                    let overlaps = !set.intersect(label_set).empty();
+                    if overlaps {
+                        merged = merged.merge(set);
+                    }
+                    !overlaps
+                });
+                edge_sets.push(merged);
+            }
+        }
+        edge_sets
+    }
+    // Returns the position of the vertex of cell idx for edge_set.
+    fn vertex(&self, idx: Index, edge_set: BitSet) -> [S; 3] {
+        let planes: Vec<Plane<S>> = edge_set
+            .map(|edge| {
+                self.crossings[&EdgeIndex {
+                    edge: Edge::from_usize(edge),
+                    index: idx,
+                }
+                .base()]
+            })
+            .collect();
+        let cell_origin = self.grid_point(idx);
+        let mut qef = Qef::new(
+            &planes,
+            BoundingBox::new(&cell_origin, &(cell_origin + self.res)),
+        );
+        qef.solve();
+        [qef.solution.x, qef.solution.y, qef.solution.z]
+    }
+    fn generate_meshes(&self) -> Vec<Mesh<S>> {
+        let mut meshes: Vec<Mesh<S>> = self.functions.iter().map(|_| Mesh::default()).collect();
+        // All vertices, with their index in the mesh of each material.
+        let mut vertices: Vec<([S; 3], HashMap<usize, usize>)> = Vec::new();
+        let mut vertex_index = HashMap::new();
+        let mut edge_sets = HashMap::new();
+        for (edge_index, _) in self.crossings.iter() {
+            if edge_index.index.contains(&0) {
+                // The cells around this edge are outside of the grid.
+                continue;
+            }
+            let mut p = Vec::with_capacity(4);
+            for (quad_edge, cell) in quad_cells(edge_index) {
+                let sets = edge_sets
+                    .entry(cell)
+                    .or_insert_with(|| self.cell_edge_sets(cell));
+                let set = *sets
+                    .iter()
+                    .find(|set| set.get(quad_edge as usize))
+                    .expect("edge without vertex");
+                let i = *vertex_index.entry((cell, set)).or_insert_with(|| {
+                    vertices.push((self.vertex(cell, set), HashMap::new()));
+                    vertices.len() - 1
+                });
+                if !p.contains(&i) {
+                    p.push(i);
+                }
+            }
+            let mut adjacent = edge_index.index;
+            adjacent[edge_index.edge as usize] += 1;
+            let labels = [self.labels[&edge_index.index], self.labels[&adjacent]];
+            for (side, &label) in labels.iter().enumerate() {
+                if label == self.void() {
+                    continue;
+                }
+                let mesh = &mut meshes[label];
+                let mut local: Vec<usize> = p
+                    .iter()
+                    .map(|&i| {
+                        let (v, local_index) = &mut vertices[i];
+                        *local_index.entry(label).or_insert_with(|| mesh.add_vertex(*v))
+                    })
+                    .collect();
+                // The material at the start of the edge is inside, like in
+                // ManifoldDualContouring::compute_quad.
+                push_quad(mesh, &mut local, side == 0, Some(label as u32));
+            }
+        }
+        meshes
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::MultiMaterial;
+    use crate::{test_shapes::Sphere, HalfEdgeMesh, ImplicitFunction, TessellationOptions};
+    use nalgebra as na;
+    use std::collections::HashSet;
+
+    // Two parallel slabs of thickness 2 * half_width, gap apart, cut off by the unit sphere.
+    struct Slabs {
+        normal: na::Vector3<f64>,
+        half_width: f64,
+        gap: f64,
+    }
+
+    impl Slabs {
+        // Returns the value and the gradient of the closest slab or the sphere.
+        fn eval(&self, p: &na::Point3<f64>) -> (f64, na::Vector3<f64>) {
+            let d = self.normal.dot(&p.coords);
+            let offset = self.gap / 2. + self.half_width;
+            let slab = [d - offset, d + offset]
+                .map(|x| (x.abs() - self.half_width, self.normal * x.signum()));
+            let slabs = if slab[0].0 < slab[1].0 { slab[0] } else { slab[1] };
+            let sphere = (p.coords.norm() - 1., p.coords.normalize());
+            if slabs.0 > sphere.0 { slabs } else { sphere }
+        }
+    }
+
+    impl ImplicitFunction<f64> for Slabs {
+        fn value(&self, p: &na::Point3<f64>) -> f64 {
+            self.eval(p).0
+        }
+        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+            self.eval(p).1
+        }
+    }
+
+    #[test]
+    fn overlapping_spheres() -> Result<(), Box<dyn std::error::Error>> {
+        let a = Sphere {
+            center: na::Point3::new(-0.4, 0., 0.),
+            radius: 0.7,
+        };
+        let b = Sphere {
+            center: na::Point3::new(0.4, 0.1, 0.),
+            radius: 0.7,
+        };
+        let meshes = MultiMaterial::new(&[&a, &b], 0.1).tessellate();
+        assert_eq!(meshes.len(), 2);
+        for (material, mesh) in meshes.iter().enumerate() {
+            assert!(mesh.faces.len() > 100);
+            mesh.is_closed()?;
+            assert!(mesh.face_tags.iter().all(|&t| t == material as u32));
+        }
+        // The interface is in both meshes with identical vertices.
+        let bits = |v: &[f64; 3]| v.map(f64::to_bits);
+        let a_vertices: HashSet<_> = meshes[0].vertices.iter().map(bits).collect();
+        let shared: Vec<_> = meshes[1]
+            .vertices
+            .iter()
+            .filter(|v| a_vertices.contains(&bits(v)))
+            .collect();
+        assert!(shared.len() > 20);
+        for v in shared {
+            assert!(v[0].abs() < 0.15, "{v:?}");
+        }
+        Ok(())
+    }
+    #[test]
+    fn separate_thin_slabs() -> Result<(), Box<dyn std::error::Error>> {
+        // Cells between the slabs have two separate sheets of the material.
+        for (i, normal) in [[1., 1., 1.], [1., 2., 0.]].into_iter().enumerate() {
+            let slabs = Slabs {
+                normal: na::Vector3::from(normal).normalize(),
+                half_width: 0.04 + 0.01 * i as f64,
+                gap: 0.1,
+            };
+            let meshes = MultiMaterial::new(&[&slabs], 0.1).tessellate();
+            meshes[0].is_closed()?;
+            HalfEdgeMesh::from_mesh(&meshes[0])?;
+        }
+        Ok(())
+    }
+    #[test]
+    #[should_panic(expected = "not supported")]
+    fn unsupported_options() {
+        let a = Sphere {
+            center: na::Point3::origin(),
+            radius: 0.7,
+        };
+        let options = TessellationOptions::new(0.1).face_budget(100);
+        MultiMaterial::with_options(&[&a], options);
+    }
+}
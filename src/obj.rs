@@ -0,0 +1,82 @@
+use crate::mesh::{invalid_data, Mesh};
+use crate::RealField;
+use nalgebra as na;
+use std::io::{self, BufRead};
+
+// Parse a vertex reference of a face, like "3", "3/1", "3//2" or "-1", into a vertex index.
+fn vertex_index(token: &str, num_vertices: usize) -> io::Result<usize> {
+    let index = token.split('/').next().unwrap_or_default();
+    let index: i64 = index
+        .parse()
+        .map_err(|_| invalid_data(format!("invalid OBJ vertex reference {:?}", token)))?;
+    // Indexes start at 1, negative ones count back from the last vertex.
+    let index = match index {
+        i if i > 0 => i - 1,
+        i => num_vertices as i64 + i,
+    };
+    usize::try_from(index).map_err(|_| invalid_data(format!("invalid OBJ vertex {:?}", token)))
+}
+
+impl<S: RealField> Mesh<S> {
+    /// Read the vertices and faces of a Wavefront OBJ file.
+    ///
+    /// Polygons are split into fans of triangles. Texture coordinates, normals, groups and
+    /// materials are ignored.
+    pub fn read_obj<R: BufRead>(reader: R) -> io::Result<Mesh<S>> {
+        let mut mesh = Mesh::default();
+        let mut polygon = Vec::new();
+        for line in reader.lines() {
+            let line = line?;
+            let mut tokens = line.split('#').next().unwrap_or_default().split_whitespace();
+            match tokens.next() {
+                Some("v") => {
+                    let mut v = [S::zero(); 3];
+                    for c in &mut v {
+                        let token = tokens.next().unwrap_or_default();
+                        let value: f64 = token.parse().map_err(|_| {
+                            invalid_data(format!("invalid OBJ coordinate {:?}", token))
+                        })?;
+                        *c = na::convert(value);
+                    }
+                    mesh.vertices.push(v);
+                }
+                Some("f") => {
+                    polygon.clear();
+                    for token in tokens {
+                        polygon.push(vertex_index(token, mesh.vertices.len())?);
+                    }
+                    mesh.add_polygon(&polygon)?;
+                }
+                _ => {}
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn quad_and_references() {
+        let obj = "# two faces\n\
+                   v 0 0 0\n\
+                   v 1 0 0\n\
+                   v 1 1 0\n\
+                   v 0 1 0 # comment\n\
+                   vt 0 0\n\
+                   vn 0 0 1\n\
+                   g square\n\
+                   f 1/1/1 2/1/1 3//1 4\n\
+                   v 0.5 0.5 1.5\n\
+                   f -4 -3 -1\n";
+        let mesh = Mesh::<f64>::read_obj(obj.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices.len(), 5);
+        assert_eq!(mesh.vertices[4], [0.5, 0.5, 1.5]);
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3], [1, 2, 4]]);
+
+        assert!(Mesh::<f64>::read_obj("v 0 0 0\nf 1 2 3\n".as_bytes()).is_err());
+        assert!(Mesh::<f64>::read_obj("v 0 0 x\n".as_bytes()).is_err());
+    }
+}
@@ -0,0 +1,192 @@
+use crate::{ClipRegion, RealField};
+use nalgebra as na;
+use num_traits::Float;
+
+// Thresholds that decide, whether a sub-cube is flat enough to be filled from a plane.
+#[derive(Clone, Copy, Debug)]
+pub(crate) struct AdaptiveSampling<S> {
+    // Cosine of the maximum angle between any sampled normal and the mean normal.
+    pub min_cos: S,
+    // Maximum deviation of the function from the fitted plane, relative to res.
+    pub relative_error: S,
+}
+
+/// Settings for [`ManifoldDualContouring`](crate::ManifoldDualContouring).
+///
+/// Create them with [`new`](Self::new) and adjust them with the builder methods:
+///
+/// ```rust
+/// let options = tessellation::TessellationOptions::new(0.1f64)
+///     .relative_error(0.05)
+///     .manifold_check(false)
+///     .threads(1);
+/// ```
+#[derive(Clone, Copy, Debug)]
+pub struct TessellationOptions<S: RealField> {
+    pub(crate) res: na::Vector3<S>,
+    pub(crate) lattice_origin: Option<na::Point3<S>>,
+    pub(crate) relative_error: S,
+    pub(crate) face_budget: Option<usize>,
+    pub(crate) simplification: bool,
+    pub(crate) manifold_check: bool,
+    pub(crate) precision: S,
+    pub(crate) max_march_distance: S,
+    pub(crate) max_expand_steps: usize,
+    pub(crate) padding: Option<S>,
+    pub(crate) threads: usize,
+    pub(crate) adaptive: Option<AdaptiveSampling<S>>,
+    pub(crate) clip: Option<ClipRegion<S>>,
+    pub(crate) projection: Option<S>,
+}
+
+impl<S: RealField + Float + From<f32>> TessellationOptions<S> {
+    /// Default options for a grid resolution of `res` along all axes.
+    pub fn new(res: S) -> Self {
+        TessellationOptions {
+            res: na::Vector3::new(res, res, res),
+            lattice_origin: None,
+            relative_error: From::from(0f32),
+            face_budget: None,
+            simplification: true,
+            manifold_check: true,
+            precision: From::from(0.05f32),
+            max_march_distance: From::from(1000f32),
+            max_expand_steps: 10_000,
+            padding: None,
+            threads: 0,
+            adaptive: None,
+            clip: None,
+            projection: None,
+        }
+    }
+    /// Use a separate grid resolution for each axis, e.g. for thin sheets or voxel data with
+    /// non-cubic spacing. Replaces the resolution given to [`new`](Self::new).
+    ///
+    /// All settings relative to `res` are relative to the smallest component of `res`.
+    pub fn resolution(mut self, res: na::Vector3<S>) -> Self {
+        self.res = res;
+        self
+    }
+    /// Align the grid to the lattice `lattice_origin + i * res`. Default: the grid starts at the
+    /// bounding box of the surface.
+    pub fn lattice_origin(mut self, lattice_origin: na::Point3<S>) -> Self {
+        self.lattice_origin = Some(lattice_origin);
+        self
+    }
+    /// Acceptable error when simplifying the mesh, relative to `res`. Default: 0.
+    pub fn relative_error(mut self, relative_error: S) -> Self {
+        self.relative_error = relative_error;
+        self
+    }
+    /// Simplify the mesh down to about `face_budget` triangles instead of using a fixed error
+    /// threshold. Default: disabled.
+    ///
+    /// Octree vertices are merged in order of increasing QEF error, until the mesh has at most
+    /// `face_budget` triangles. The number of merged vertices is binary searched, and each step
+    /// of the search counts the faces of the whole mesh once. Vertices that would break the
+    /// manifold check are never merged, so the budget might not be met, which
+    /// [`face_budget_met`](crate::ManifoldDualContouring::face_budget_met) reports. Then the mesh
+    /// is simplified as far as possible. Replaces [`relative_error`](Self::relative_error).
+    /// Ignored by chunked and periodic tessellation.
+    pub fn face_budget(mut self, face_budget: usize) -> Self {
+        self.face_budget = Some(face_budget);
+        self
+    }
+    /// Merge vertices in the octree, if their error is below the threshold. Default: true.
+    ///
+    /// Without simplification the octree is not built at all and the mesh has one vertex per
+    /// cell and surface sheet.
+    pub fn simplification(mut self, simplification: bool) -> Self {
+        self.simplification = simplification;
+        self
+    }
+    /// Only merge vertices, if the result is still a 2-manifold. Default: true.
+    ///
+    /// Disabling the check simplifies more aggressively, but the mesh might not be a manifold
+    /// anymore.
+    pub fn manifold_check(mut self, manifold_check: bool) -> Self {
+        self.manifold_check = manifold_check;
+        self
+    }
+    /// Accuracy of the edge crossings, relative to the edge length. Default: 0.05.
+    pub fn precision(mut self, precision: S) -> Self {
+        self.precision = precision;
+        self
+    }
+    /// Maximum distance from the first point inside the object, at which the surface is
+    /// searched when finding the bounds of the function. Default: 1000.
+    pub fn max_march_distance(mut self, max_march_distance: S) -> Self {
+        self.max_march_distance = max_march_distance;
+        self
+    }
+    /// Maximum number of steps each face of the bounding box is expanded, until the surface is
+    /// enclosed. Default: 10000.
+    pub fn max_expand_steps(mut self, max_expand_steps: usize) -> Self {
+        self.max_expand_steps = max_expand_steps;
+        self
+    }
+    /// Distance the grid extends beyond the bounding box of the surface. Default: `1 + 1.1 * res`.
+    pub fn padding(mut self, padding: S) -> Self {
+        self.padding = Some(padding);
+        self
+    }
+    /// Number of threads used to compact the sampled values, the only parallel stage. The pool is
+    /// built once per tessellator and shared by all chunks. Default: 0, which uses rayon's global
+    /// pool.
+    pub fn threads(mut self, threads: usize) -> Self {
+        self.threads = threads;
+        self
+    }
+    /// Enable adaptive sampling. Default: disabled.
+    ///
+    /// Skips the value samples inside flat sub-cubes of the sampling octree. A sub-cube is
+    /// considered flat, if the normals sampled at its corners and its center deviate at most
+    /// `max_normal_angle` (in radians) from their mean and the function values deviate at most
+    /// `res * relative_error` from a plane along that mean normal. Only the offset of the plane is
+    /// fitted to the values, its orientation is not. The grid values of flat sub-cubes are taken
+    /// from that plane instead of the function.
+    ///
+    /// Only the sampling stage gets cheaper. Edge crossings are still searched with the function,
+    /// and the grid, the leaf cells and thus the memory use stay at full resolution. Close to the
+    /// surface, the sign of a filled value can disagree with the function, which moves the
+    /// surface by up to the allowed error. Features smaller than a flat sub-cube that are missed
+    /// by all nine samples will be lost.
+    pub fn adaptive_sampling(mut self, max_normal_angle: S, relative_error: S) -> Self {
+        self.adaptive = Some(AdaptiveSampling {
+            min_cos: Float::cos(max_normal_angle),
+            relative_error,
+        });
+        self
+    }
+    /// Intersect the function with `clip` while sampling. Default: no clipping.
+    ///
+    /// The cut is capped, so the mesh is still closed. Cap faces are tagged with
+    /// [`CAP_FACE_TAG`](crate::CAP_FACE_TAG) in [`Mesh::face_tags`](crate::Mesh::face_tags), all
+    /// other faces with 0.
+    pub fn clip(mut self, clip: ClipRegion<S>) -> Self {
+        self.clip = Some(clip);
+        self
+    }
+    /// Move each vertex onto the surface with Newton steps along the normal after it is placed.
+    /// Default: disabled.
+    ///
+    /// QEF solutions are clamped to their cell and might be off the surface, which this corrects
+    /// for measuring tasks. A projected vertex may leave its cell by at most `tolerance`, relative
+    /// to `res`. Otherwise it stays where it is, so that the topology of the mesh is kept.
+    pub fn project_vertices(mut self, tolerance: S) -> Self {
+        self.projection = Some(tolerance);
+        self
+    }
+    // Returns the smallest component of res.
+    pub(crate) fn min_res(&self) -> S {
+        self.res.min()
+    }
+    // Returns the padding of the grid per axis.
+    pub(crate) fn grid_padding(&self) -> na::Vector3<S> {
+        let one: S = From::from(1f32);
+        match self.padding {
+            Some(padding) => na::Vector3::new(padding, padding, padding),
+            None => self.res.map(|res| one + res * From::from(1.1f32)),
+        }
+    }
+}
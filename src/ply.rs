@@ -0,0 +1,530 @@
+use crate::mesh::{invalid_data, Mesh, MeshSink};
+use crate::RealField;
+use nalgebra as na;
+use std::io::{self, BufRead, Seek, SeekFrom, Write};
+
+// Vertex attributes with these names are colour channels. They are written as uchar, which is
+// what viewers expect, mapping [0, 1] to [0, 255].
+const COLOR_CHANNELS: [&str; 4] = ["red", "green", "blue", "alpha"];
+
+fn is_color(name: &str) -> bool {
+    COLOR_CHANNELS.contains(&name)
+}
+
+// Returns the PLY header. The counts are zero padded, so that the header has the same length for
+// all counts and can be patched in place.
+fn header(num_vertices: usize, num_faces: usize, attributes: &[String]) -> String {
+    let properties: String = attributes
+        .iter()
+        .map(|name| {
+            let ty = if is_color(name) { "uchar" } else { "float" };
+            format!("property {} {}\n", ty, name)
+        })
+        .collect();
+    format!(
+        "ply\n\
+         format binary_little_endian 1.0\n\
+         comment written by tessellation\n\
+         element vertex {:010}\n\
+         property float x\n\
+         property float y\n\
+         property float z\n\
+         {}\
+         element face {:010}\n\
+         property list uchar int vertex_indices\n\
+         end_header\n",
+        num_vertices, properties, num_faces
+    )
+}
+
+/// Streaming writer for binary little endian PLY files.
+///
+/// PLY stores all vertices before all faces. This writer writes each vertex as soon as it is added
+/// and keeps the faces in memory until [`finish`](Self::finish) appends them and patches the
+/// counts in the header.
+///
+/// Vertex attributes are written as additional vertex properties, if they were declared with
+/// [`with_vertex_attributes`](Self::with_vertex_attributes).
+pub struct PlyWriter<W: Write + Seek> {
+    writer: W,
+    start: u64,
+    attributes: Vec<String>,
+    num_vertices: usize,
+    faces: Vec<[u32; 3]>,
+    // The first error that occured while writing. All later writes are skipped.
+    error: Option<io::Error>,
+}
+
+impl<W: Write + Seek> PlyWriter<W> {
+    /// Create a new writer and write a preliminary PLY header to writer.
+    pub fn new(writer: W) -> io::Result<PlyWriter<W>> {
+        Self::with_vertex_attributes(writer, &[])
+    }
+    /// Create a new writer, which stores a vertex property for each name in `attributes`.
+    ///
+    /// The properties are filled with the vertex attributes in the same order. Properties named
+    /// `red`, `green`, `blue` and `alpha` are colour channels, which are mapped from [0, 1] to
+    /// a uchar. All others are written as float. Vertices without attributes get zeros.
+    pub fn with_vertex_attributes(mut writer: W, attributes: &[&str]) -> io::Result<PlyWriter<W>> {
+        let attributes: Vec<String> = attributes.iter().map(|a| a.to_string()).collect();
+        let start = writer.stream_position()?;
+        writer.write_all(header(0, 0, &attributes).as_bytes())?;
+        Ok(PlyWriter {
+            writer,
+            start,
+            attributes,
+            num_vertices: 0,
+            faces: Vec::new(),
+            error: None,
+        })
+    }
+    /// Write all faces, patch the header and return the underlying writer, or the first error that
+    /// occured while writing.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let mut record = [3u8; 13];
+        for f in &self.faces {
+            for (i, c) in f.iter().enumerate() {
+                record[1 + i * 4..5 + i * 4].copy_from_slice(&c.to_le_bytes());
+            }
+            self.writer.write_all(&record)?;
+        }
+        let end = self.writer.stream_position()?;
+        self.writer.seek(SeekFrom::Start(self.start))?;
+        self.writer
+            .write_all(header(self.num_vertices, self.faces.len(), &self.attributes).as_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+    fn write_vertex(&mut self, v: [f32; 3], attributes: &[f32]) -> io::Result<()> {
+        if !attributes.is_empty() && attributes.len() != self.attributes.len() {
+            return Err(io::Error::other(format!(
+                "{} vertex attributes, but the PLY header declares {}",
+                attributes.len(),
+                self.attributes.len()
+            )));
+        }
+        for c in v {
+            self.writer.write_all(&c.to_le_bytes())?;
+        }
+        for (i, name) in self.attributes.iter().enumerate() {
+            let a = attributes.get(i).copied().unwrap_or(0.);
+            if is_color(name) {
+                self.writer.write_all(&[(a.clamp(0., 1.) * 255.).round() as u8])?;
+            } else {
+                self.writer.write_all(&a.to_le_bytes())?;
+            }
+        }
+        Ok(())
+    }
+    fn add_vertex_record(&mut self, v: [f32; 3], attributes: &[f32]) -> usize {
+        if self.error.is_none() {
+            self.error = self.write_vertex(v, attributes).err();
+        }
+        self.num_vertices += 1;
+        self.num_vertices - 1
+    }
+}
+
+impl<W: Write + Seek, S> MeshSink<S> for PlyWriter<W>
+where
+    f64: From<S>,
+{
+    fn add_vertex(&mut self, v: [S; 3]) -> usize {
+        self.add_vertex_record(v.map(|c| f64::from(c) as f32), &[])
+    }
+    fn add_vertex_with_attributes(&mut self, v: [S; 3], attributes: &[f32]) -> usize {
+        self.add_vertex_record(v.map(|c| f64::from(c) as f32), attributes)
+    }
+    fn add_triangle(&mut self, f: [usize; 3]) {
+        match f.map(u32::try_from) {
+            [Ok(a), Ok(b), Ok(c)] => self.faces.push([a, b, c]),
+            _ => {
+                if self.error.is_none() {
+                    self.error = Some(io::Error::other("too many vertices for PLY"));
+                }
+            }
+        }
+    }
+}
+
+// Scalar types of PLY properties.
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Type {
+    I8,
+    U8,
+    I16,
+    U16,
+    I32,
+    U32,
+    F32,
+    F64,
+}
+
+impl Type {
+    fn parse(name: &str) -> io::Result<Type> {
+        Ok(match name {
+            "char" | "int8" => Type::I8,
+            "uchar" | "uint8" => Type::U8,
+            "short" | "int16" => Type::I16,
+            "ushort" | "uint16" => Type::U16,
+            "int" | "int32" => Type::I32,
+            "uint" | "uint32" => Type::U32,
+            "float" | "float32" => Type::F32,
+            "double" | "float64" => Type::F64,
+            _ => return Err(invalid_data(format!("unknown PLY type {:?}", name))),
+        })
+    }
+    fn size(self) -> usize {
+        match self {
+            Type::I8 | Type::U8 => 1,
+            Type::I16 | Type::U16 => 2,
+            Type::I32 | Type::U32 | Type::F32 => 4,
+            Type::F64 => 8,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct Property {
+    name: String,
+    ty: Type,
+    // Type of the length, if this is a list property.
+    list: Option<Type>,
+}
+
+#[derive(Debug)]
+struct Element {
+    name: String,
+    count: usize,
+    properties: Vec<Property>,
+}
+
+impl Element {
+    // Returns the smallest number of bytes a record of this element takes in the body. In ASCII,
+    // every value takes at least one character.
+    fn min_size(&self, format: Format) -> usize {
+        self.properties
+            .iter()
+            .map(|p| match format {
+                Format::Ascii => 1,
+                _ => p.list.unwrap_or(p.ty).size(),
+            })
+            .sum()
+    }
+}
+
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Format {
+    Ascii,
+    LittleEndian,
+    BigEndian,
+}
+
+// Reads the values of the PLY body in any of the formats as f64.
+struct Body {
+    data: Vec<u8>,
+    position: usize,
+    format: Format,
+}
+
+impl Body {
+    fn value(&mut self, ty: Type) -> io::Result<f64> {
+        if self.format == Format::Ascii {
+            let rest = &self.data[self.position..];
+            let start = rest.iter().position(|c| !c.is_ascii_whitespace());
+            let start = start.ok_or_else(|| invalid_data("PLY body is too short"))?;
+            let len = rest[start..].iter().take_while(|c| !c.is_ascii_whitespace()).count();
+            self.position += start + len;
+            let token = std::str::from_utf8(&rest[start..start + len]).unwrap_or_default();
+            return token
+                .parse()
+                .map_err(|_| invalid_data(format!("invalid PLY value {:?}", token)));
+        }
+        let bytes = self
+            .data
+            .get(self.position..self.position + ty.size())
+            .ok_or_else(|| invalid_data("PLY body is too short"))?;
+        self.position += ty.size();
+        let mut buffer = [0u8; 8];
+        buffer[..bytes.len()].copy_from_slice(bytes);
+        if self.format == Format::BigEndian {
+            buffer[..bytes.len()].reverse();
+        }
+        let [b0, b1, b2, b3, ..] = buffer;
+        Ok(match ty {
+            Type::I8 => b0 as i8 as f64,
+            Type::U8 => b0 as f64,
+            Type::I16 => i16::from_le_bytes([b0, b1]) as f64,
+            Type::U16 => u16::from_le_bytes([b0, b1]) as f64,
+            Type::I32 => i32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Type::U32 => u32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Type::F32 => f32::from_le_bytes([b0, b1, b2, b3]) as f64,
+            Type::F64 => f64::from_le_bytes(buffer),
+        })
+    }
+}
+
+// Convert a list length or vertex index to usize. Fractions, negative values and values out of
+// range are rejected instead of being truncated.
+fn to_usize(value: f64) -> io::Result<usize> {
+    if value.fract() == 0. && value >= 0. && value < usize::MAX as f64 {
+        Ok(value as usize)
+    } else {
+        Err(invalid_data(format!("invalid PLY index {}", value)))
+    }
+}
+
+// Parse the header up to and including end_header.
+fn read_header<R: BufRead>(reader: &mut R) -> io::Result<(Format, Vec<Element>)> {
+    let mut line = String::new();
+    reader.read_line(&mut line)?;
+    if line.trim_end() != "ply" {
+        return Err(invalid_data("not a PLY file"));
+    }
+    let mut format = None;
+    let mut elements: Vec<Element> = Vec::new();
+    loop {
+        line.clear();
+        if reader.read_line(&mut line)? == 0 {
+            return Err(invalid_data("PLY header without end_header"));
+        }
+        let tokens: Vec<&str> = line.split_whitespace().collect();
+        match tokens.as_slice() {
+            ["format", "ascii", _] => format = Some(Format::Ascii),
+            ["format", "binary_little_endian", _] => format = Some(Format::LittleEndian),
+            ["format", "binary_big_endian", _] => format = Some(Format::BigEndian),
+            ["element", name, count] => elements.push(Element {
+                name: name.to_string(),
+                count: count
+                    .parse()
+                    .map_err(|_| invalid_data(format!("invalid PLY element count {:?}", count)))?,
+                properties: Vec::new(),
+            }),
+            ["property", "list", length, ty, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid_data("PLY property before element"))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    ty: Type::parse(ty)?,
+                    list: Some(Type::parse(length)?),
+                });
+            }
+            ["property", ty, name] => {
+                let element = elements
+                    .last_mut()
+                    .ok_or_else(|| invalid_data("PLY property before element"))?;
+                element.properties.push(Property {
+                    name: name.to_string(),
+                    ty: Type::parse(ty)?,
+                    list: None,
+                });
+            }
+            ["end_header"] => break,
+            _ => {}
+        }
+    }
+    let format = format.ok_or_else(|| invalid_data("PLY header without format"))?;
+    Ok((format, elements))
+}
+
+impl<S: RealField> Mesh<S> {
+    /// Read the vertices and faces of an ASCII or binary PLY file.
+    ///
+    /// Scalar vertex properties other than the coordinates are read as vertex attributes, in the
+    /// order of the header. Colour channels stored as uchar are mapped to [0, 1], like
+    /// [`PlyWriter`] writes them. Polygons are split into fans of triangles. Other elements are
+    /// skipped.
+    pub fn read_ply<R: BufRead>(mut reader: R) -> io::Result<Mesh<S>> {
+        let (format, elements) = read_header(&mut reader)?;
+        let mut body = Body {
+            data: Vec::new(),
+            position: 0,
+            format,
+        };
+        reader.read_to_end(&mut body.data)?;
+        let mut mesh = Mesh::default();
+        let mut values = Vec::new();
+        let mut polygon = Vec::new();
+        let mut attributes = Vec::new();
+        for element in &elements {
+            let position = |name: &str| element.properties.iter().position(|p| p.name == name);
+            let coordinates = [position("x"), position("y"), position("z")];
+            let indexes = position("vertex_indices").or(position("vertex_index"));
+            // Records without properties take no bytes, so nothing limits their count.
+            let min_size = element.min_size(format);
+            if min_size == 0 {
+                continue;
+            }
+            if element.count.saturating_mul(min_size) > body.data.len() - body.position {
+                return Err(invalid_data("PLY body is too short"));
+            }
+            for _ in 0..element.count {
+                values.clear();
+                polygon.clear();
+                for (i, property) in element.properties.iter().enumerate() {
+                    let Some(length) = property.list else {
+                        values.push(body.value(property.ty)?);
+                        continue;
+                    };
+                    let length = to_usize(body.value(length)?)?;
+                    for _ in 0..length {
+                        let value = body.value(property.ty)?;
+                        if Some(i) == indexes {
+                            polygon.push(to_usize(value)?);
+                        }
+                    }
+                    // Keep the positions of the scalar properties in values.
+                    values.push(0.);
+                }
+                if element.name == "vertex" {
+                    let [Some(x), Some(y), Some(z)] = coordinates else {
+                        return Err(invalid_data("PLY vertex without coordinates"));
+                    };
+                    attributes.clear();
+                    attributes.extend(
+                        element
+                            .properties
+                            .iter()
+                            .zip(&values)
+                            .filter(|(p, _)| {
+                                p.list.is_none() && !["x", "y", "z"].contains(&&*p.name)
+                            })
+                            .map(|(p, &value)| match p.ty {
+                                Type::U8 if is_color(&p.name) => (value / 255.) as f32,
+                                _ => value as f32,
+                            }),
+                    );
+                    let position = [x, y, z].map(|i| na::convert(values[i]));
+                    mesh.add_vertex_with_attributes(position, &attributes);
+                } else if element.name == "face" && indexes.is_some() {
+                    mesh.add_polygon(&polygon)?;
+                }
+            }
+        }
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    #[test]
+    fn header_and_records() {
+        let mut ply = PlyWriter::new(Cursor::new(Vec::new())).unwrap();
+        for v in [[0., 0., 0.], [1., 0., 0.], [0., 1., 0.]] {
+            MeshSink::<f32>::add_vertex(&mut ply, v);
+        }
+        MeshSink::<f32>::add_triangle(&mut ply, [0, 1, 2]);
+        let data = ply.finish().unwrap().into_inner();
+        let expected_header = header(3, 1, &[]);
+        assert!(expected_header.contains("element vertex 0000000003\n"));
+        assert!(data.starts_with(expected_header.as_bytes()));
+        let body = &data[expected_header.len()..];
+        assert_eq!(body.len(), 3 * 12 + 13);
+        assert_eq!(&body[12..16], &1f32.to_le_bytes());
+        assert_eq!(body[36], 3);
+        assert_eq!(&body[37..], &[0, 0, 0, 0, 1, 0, 0, 0, 2, 0, 0, 0]);
+    }
+
+    #[test]
+    fn vertex_colors() {
+        let attributes = ["red", "density"];
+        let mut ply = PlyWriter::with_vertex_attributes(Cursor::new(Vec::new()), &attributes).unwrap();
+        MeshSink::<f32>::add_vertex_with_attributes(&mut ply, [0., 0., 0.], &[1., 0.25]);
+        MeshSink::<f32>::add_vertex(&mut ply, [1., 0., 0.]);
+        let data = ply.finish().unwrap().into_inner();
+        let expected_header = header(2, 0, &["red".into(), "density".into()]);
+        assert!(expected_header.contains("property uchar red\nproperty float density\n"));
+        assert!(data.starts_with(expected_header.as_bytes()));
+        let body = &data[expected_header.len()..];
+        assert_eq!(body.len(), 2 * 17);
+        assert_eq!(body[12], 255);
+        assert_eq!(&body[13..17], &0.25f32.to_le_bytes());
+        assert_eq!(&body[29..34], &[0, 0, 0, 0, 0]);
+
+        let mut ply = PlyWriter::with_vertex_attributes(Cursor::new(Vec::new()), &["red"]).unwrap();
+        MeshSink::<f32>::add_vertex_with_attributes(&mut ply, [0., 0., 0.], &[1., 1.]);
+        assert!(ply.finish().is_err());
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let attributes = ["red", "density"];
+        let mut ply = PlyWriter::with_vertex_attributes(Cursor::new(Vec::new()), &attributes).unwrap();
+        let vertices = [[0., 0., 0.], [1., 0., 0.], [0., 1., 0.], [0., 0., 1.]];
+        for (i, v) in vertices.iter().enumerate() {
+            MeshSink::<f32>::add_vertex_with_attributes(&mut ply, *v, &[1., i as f32]);
+        }
+        MeshSink::<f32>::add_triangle(&mut ply, [0, 2, 1]);
+        MeshSink::<f32>::add_triangle(&mut ply, [1, 2, 3]);
+        let data = ply.finish().unwrap().into_inner();
+        let mesh = Mesh::<f32>::read_ply(data.as_slice()).unwrap();
+        assert_eq!(mesh.vertices, vertices);
+        assert_eq!(mesh.faces, vec![[0, 2, 1], [1, 2, 3]]);
+        assert_eq!(mesh.attributes(3), [1., 3.]);
+    }
+
+    #[test]
+    fn ascii_polygons() {
+        let ply = "ply\n\
+                   format ascii 1.0\n\
+                   comment a square and an unrelated element\n\
+                   element vertex 4\n\
+                   property double x\n\
+                   property double y\n\
+                   property double z\n\
+                   element face 1\n\
+                   property list uchar uint vertex_index\n\
+                   element material 1\n\
+                   property list uchar float values\n\
+                   end_header\n\
+                   0 0 0\n\
+                   1 0 0\n\
+                   1 1 0.5\n\
+                   0 1 0\n\
+                   4 0 1 2 3\n\
+                   2 0.5 0.5\n";
+        let mesh = Mesh::<f64>::read_ply(ply.as_bytes()).unwrap();
+        assert_eq!(mesh.vertices[2], [1., 1., 0.5]);
+        assert_eq!(mesh.faces, vec![[0, 1, 2], [0, 2, 3]]);
+        assert!(mesh.vertex_attributes.is_empty());
+
+        let truncated = &ply[..ply.len() - 20];
+        assert!(Mesh::<f64>::read_ply(truncated.as_bytes()).is_err());
+        assert!(Mesh::<f64>::read_ply("solid\n".as_bytes()).is_err());
+    }
+
+    #[test]
+    fn huge_element_count() {
+        let empty = "ply\nformat ascii 1.0\nelement junk 18446744073709551615\nend_header\n";
+        assert!(Mesh::<f64>::read_ply(empty.as_bytes()).unwrap().vertices.is_empty());
+        let binary = "ply\nformat binary_little_endian 1.0\nelement vertex 1000000000\n\
+                      property float x\nproperty float y\nproperty float z\nend_header\n";
+        let error = Mesh::<f64>::read_ply(binary.as_bytes()).unwrap_err();
+        assert_eq!(error.kind(), io::ErrorKind::InvalidData);
+    }
+
+    #[test]
+    fn invalid_indexes() {
+        let ply = |face: &str| {
+            format!(
+                "ply\nformat ascii 1.0\nelement vertex 3\nproperty float x\nproperty float y\n\
+                 property float z\nelement face 1\nproperty list int int vertex_indices\n\
+                 end_header\n0 0 0\n1 0 0\n0 1 0\n{}\n",
+                face
+            )
+        };
+        assert!(Mesh::<f64>::read_ply(ply("3 0 1 2").as_bytes()).is_ok());
+        for face in ["3 0 1 -2", "3 0 1 1.5", "-3 0 1 2", "2.5 0 1 2", "3 0 1 1e30", "3 0 1 nan"] {
+            let error = Mesh::<f64>::read_ply(ply(face).as_bytes()).unwrap_err();
+            assert_eq!(error.kind(), io::ErrorKind::InvalidData, "{face}");
+        }
+    }
+}
@@ -20,7 +20,8 @@ pub struct Qef<S: RealField + Debug> {
     // Scalar BT * B
     btb: S,
     pub error: S,
-    bbox: BoundingBox<S, 3>,
+    // Cell the solution is constrained to.
+    pub bbox: BoundingBox<S, 3>,
 }
 
 impl<S: RealField + Float + Debug + From<f32>> Qef<S> {
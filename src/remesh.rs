@@ -0,0 +1,287 @@
+use crate::{
+    half_edge::{HalfEdgeError, HalfEdgeMesh},
+    mesh::{point, Mesh},
+    ImplicitFunction, RealField,
+};
+use nalgebra as na;
+use num_traits::Float;
+
+// Maximum number of Newton steps when projecting a vertex onto the surface.
+const PROJECTION_STEPS: usize = 5;
+
+// Move p onto the zero set of f with Newton steps along the normal. The derivative along the
+// normal is estimated with central differences, since values need not be distances. Each step is
+// limited to max_step, so that vertices cannot jump to other parts of the surface.
+pub(crate) fn project_to_surface<S: RealField + Float + From<f32>>(
+    f: &dyn ImplicitFunction<S>,
+    mut p: na::Point3<S>,
+    max_step: S,
+) -> na::Point3<S> {
+    let h = max_step * From::from(1e-3f32);
+    for _ in 0..PROJECTION_STEPS {
+        let value = f.value(&p);
+        let n = f.normal(&p);
+        let slope = (f.value(&(p + n * h)) - f.value(&(p - n * h))) / (h + h);
+        // Also stops on NaN.
+        if slope.partial_cmp(&S::zero()) != Some(std::cmp::Ordering::Greater) {
+            break;
+        }
+        let step = value / slope;
+        p -= n * Float::max(-max_step, Float::min(step, max_step));
+        if Float::abs(step) < h {
+            break;
+        }
+    }
+    p
+}
+
+// State of an ongoing remeshing.
+struct Remesh<S: RealField> {
+    mesh: HalfEdgeMesh<S>,
+}
+
+impl<S: RealField + Float + From<f32>> Remesh<S> {
+    fn position(&self, v: usize) -> na::Point3<S> {
+        point(&self.mesh.position(v))
+    }
+    fn length(&self, a: usize, b: usize) -> S {
+        (self.position(a) - self.position(b)).norm()
+    }
+    fn is_removed(&self, v: usize) -> bool {
+        self.mesh.one_ring(v).is_empty()
+    }
+    // Vertices on the boundary of an open mesh are neither moved nor removed.
+    fn is_boundary(&self, v: usize) -> bool {
+        self.mesh.is_boundary_vertex(v)
+    }
+    // Returns the half-edge between a and b in either direction.
+    fn half_edge(&self, a: usize, b: usize) -> Option<usize> {
+        self.mesh.half_edge(a, b).or(self.mesh.half_edge(b, a))
+    }
+    // Returns all edges as sorted pairs of vertices.
+    fn edges(&self) -> Vec<(usize, usize)> {
+        let mut edges: Vec<(usize, usize)> = (0..self.mesh.num_faces())
+            .filter_map(|f| self.mesh.face(f))
+            .flat_map(|[a, b, c]| [(a, b), (b, c), (c, a)])
+            .map(|(a, b)| (a.min(b), a.max(b)))
+            .collect();
+        edges.sort_unstable();
+        edges.dedup();
+        edges
+    }
+    fn normal(&self, f: usize) -> na::Vector3<S> {
+        let [a, b, c] = self.mesh.face(f).unwrap().map(|v| self.position(v));
+        (b - a).cross(&(c - a))
+    }
+    fn vertex_normal(&self, v: usize) -> Option<na::Vector3<S>> {
+        self.mesh
+            .vertex_faces(v)
+            .into_iter()
+            .map(|f| self.normal(f))
+            .fold(na::Vector3::zeros(), |sum: na::Vector3<S>, n| sum + n)
+            .try_normalize(S::zero())
+    }
+    fn split(&mut self, a: usize, b: usize) {
+        let p = na::center(&self.position(a), &self.position(b));
+        let h = self.half_edge(a, b).unwrap();
+        self.mesh.split(h, [p.x, p.y, p.z]);
+    }
+    // Returns whether moving a and b to p keeps all faces but the ones of the edge a-b from
+    // flipping.
+    fn keeps_orientation(&self, a: usize, b: usize, p: &na::Point3<S>) -> bool {
+        [a, b].iter().all(|&v| {
+            self.mesh.vertex_faces(v).into_iter().all(|f| {
+                let face = self.mesh.face(f).unwrap();
+                if face.contains(&a) && face.contains(&b) {
+                    return true;
+                }
+                let corners = face.map(|u| if u == a || u == b { *p } else { self.position(u) });
+                let after = (corners[1] - corners[0]).cross(&(corners[2] - corners[0]));
+                after.dot(&self.normal(f)) > S::zero()
+            })
+        })
+    }
+    // Collapse the edge a-b to its midpoint, unless that creates an edge longer than max_length,
+    // breaks the manifold property or flips a face.
+    fn collapse(&mut self, a: usize, b: usize, max_length: S) {
+        if self.is_boundary(a) || self.is_boundary(b) {
+            return;
+        }
+        let Some(h) = self.mesh.half_edge(b, a) else {
+            return;
+        };
+        let p = na::center(&self.position(a), &self.position(b));
+        let too_long = |v: usize| (self.position(v) - p).norm() > max_length;
+        if self.mesh.one_ring(a).into_iter().chain(self.mesh.one_ring(b)).any(too_long) {
+            return;
+        }
+        if self.keeps_orientation(a, b, &p) {
+            self.mesh.collapse(h, [p.x, p.y, p.z]);
+        }
+    }
+    fn valence_deviation(&self, v: usize, change: isize) -> usize {
+        let target = if self.is_boundary(v) { 4 } else { 6 };
+        (self.mesh.one_ring(v).len() as isize + change - target).unsigned_abs()
+    }
+    // Flip the edge a-b, if that brings the valences of the four vertices closer to the optimum.
+    fn flip(&mut self, a: usize, b: usize) {
+        // f1 is a-b-c, f2 is b-a-d.
+        let (Some(h1), Some(h2)) = (self.mesh.half_edge(a, b), self.mesh.half_edge(b, a)) else {
+            return;
+        };
+        let (f1, f2) = (h1 / 3, h2 / 3);
+        // Edges between differently tagged faces are kept as feature lines.
+        if self.mesh.face_tag(f1) != self.mesh.face_tag(f2) {
+            return;
+        }
+        let c = self.mesh.to(self.mesh.next(h1));
+        let d = self.mesh.to(self.mesh.next(h2));
+        let before: usize = [a, b, c, d].iter().map(|&v| self.valence_deviation(v, 0)).sum();
+        let after = self.valence_deviation(a, -1)
+            + self.valence_deviation(b, -1)
+            + self.valence_deviation(c, 1)
+            + self.valence_deviation(d, 1);
+        if after >= before {
+            return;
+        }
+        let old_normal = self.normal(f1) + self.normal(f2);
+        let folds = [[a, d, c], [d, b, c]].iter().any(|f| {
+            let [p, q, r] = f.map(|v| self.position(v));
+            (q - p).cross(&(r - p)).dot(&old_normal) <= S::zero()
+        });
+        if !folds {
+            self.mesh.flip(h1);
+        }
+    }
+    // Move every vertex towards the centroid of its neighbors within its tangent plane.
+    fn relax(&mut self) {
+        let positions: Vec<Option<na::Point3<S>>> = (0..self.mesh.num_vertices())
+            .map(|v| {
+                let neighbors = self.mesh.one_ring(v);
+                if neighbors.is_empty() || self.is_boundary(v) {
+                    return None;
+                }
+                let n = self.vertex_normal(v)?;
+                let num: S = From::from(neighbors.len() as f32);
+                let centroid = neighbors
+                    .iter()
+                    .fold(na::Vector3::zeros(), |sum: na::Vector3<S>, &u| {
+                        sum + self.position(u).coords
+                    })
+                    / num;
+                let d = centroid - self.position(v).coords;
+                Some(self.position(v) + d - n * n.dot(&d))
+            })
+            .collect();
+        for (v, p) in positions.into_iter().enumerate() {
+            if let Some(p) = p {
+                self.mesh.set_position(v, [p.x, p.y, p.z]);
+            }
+        }
+    }
+    fn project(&mut self, f: &dyn ImplicitFunction<S>, max_step: S) {
+        for v in 0..self.mesh.num_vertices() {
+            if !self.is_removed(v) && !self.is_boundary(v) {
+                let p = project_to_surface(f, self.position(v), max_step);
+                self.mesh.set_position(v, [p.x, p.y, p.z]);
+            }
+        }
+    }
+}
+
+impl<S: RealField + Float + From<f32>> Mesh<S> {
+    /// Remesh towards triangles with edges of about `target_edge_length`, which makes them
+    /// suitable for simulations and offsetting (Botsch and Kobbelt).
+    ///
+    /// Each of the `iterations` splits edges longer than 4/3 of the target, collapses edges
+    /// shorter than 4/5 of it, flips edges to even out the vertex valences and moves vertices
+    /// towards the centroid of their neighbors within the tangent plane. If `surface` is given,
+    /// vertices are projected back onto its zero set after each iteration, otherwise the
+    /// relaxation slowly smoothes the shape. Closed manifolds stay closed manifolds, the boundary
+    /// of open meshes is kept. Fails and leaves the mesh unchanged, if it is not an oriented
+    /// 2-manifold.
+    pub fn remesh(
+        &mut self,
+        target_edge_length: S,
+        iterations: usize,
+        surface: Option<&dyn ImplicitFunction<S>>,
+    ) -> Result<(), HalfEdgeError> {
+        let high = target_edge_length * From::from(4. / 3f32);
+        let low = target_edge_length * From::from(0.8f32);
+        let mut remesh = Remesh {
+            mesh: HalfEdgeMesh::from_mesh(self)?,
+        };
+        for _ in 0..iterations {
+            for (a, b) in remesh.edges() {
+                if remesh.length(a, b) > high {
+                    remesh.split(a, b);
+                }
+            }
+            for (a, b) in remesh.edges() {
+                // Earlier collapses might have removed the edge.
+                if remesh.half_edge(a, b).is_some() && remesh.length(a, b) < low {
+                    remesh.collapse(a, b, high);
+                }
+            }
+            for (a, b) in remesh.edges() {
+                remesh.flip(a, b);
+            }
+            remesh.relax();
+            if let Some(f) = surface {
+                remesh.project(f, target_edge_length);
+            }
+        }
+        *self = remesh.mesh.to_mesh();
+        Ok(())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{test_shapes::Sphere, ManifoldDualContouring};
+    use nalgebra as na;
+
+    // Returns the smallest angle of each face in degrees.
+    fn min_angles(mesh: &crate::Mesh<f64>) -> Vec<f64> {
+        mesh.faces
+            .iter()
+            .map(|f| {
+                let p = f.map(|i| na::Point3::from(mesh.vertices[i]));
+                (0..3)
+                    .map(|i| {
+                        let u = p[(i + 1) % 3] - p[i];
+                        let w = p[(i + 2) % 3] - p[i];
+                        u.angle(&w).to_degrees()
+                    })
+                    .fold(180., f64::min)
+            })
+            .collect()
+    }
+
+    #[test]
+    fn remesh_sphere() -> Result<(), Box<dyn std::error::Error>> {
+        let mut mesh = ManifoldDualContouring::new(&Sphere::unit(), 0.1, 0.)
+            .tessellate()
+            .unwrap();
+        let slivers = |mesh: &crate::Mesh<f64>| {
+            min_angles(mesh).iter().filter(|&&a| a < 20.).count()
+        };
+        let before = slivers(&mesh);
+        mesh.remesh(0.1, 5, Some(&Sphere::unit()))?;
+        mesh.is_closed()?;
+        assert!(slivers(&mesh) * 4 < before, "{} -> {}", before, slivers(&mesh));
+        for v in &mesh.vertices {
+            assert!((na::Vector3::from(*v).norm() - 1.).abs() < 1e-3, "{v:?}");
+        }
+        let mut lengths = 0.;
+        for f in &mesh.faces {
+            for i in 0..3 {
+                let [p, q] = [f[i], f[(i + 1) % 3]].map(|v| na::Point3::from(mesh.vertices[v]));
+                lengths += (p - q).norm();
+            }
+        }
+        let mean = lengths / (3 * mesh.faces.len()) as f64;
+        assert!((mean - 0.1).abs() < 0.02, "{mean}");
+        Ok(())
+    }
+}
@@ -0,0 +1,224 @@
+use crate::{
+    adjacency::Adjacency,
+    mesh::{point, Mesh},
+    RealField,
+};
+use nalgebra as na;
+use num_traits::Float;
+
+/// Weights of the neighbors in the Laplacian used by
+/// [`Mesh::smooth_taubin_with`](crate::Mesh::smooth_taubin_with).
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum Laplacian {
+    /// All neighbors have the same weight. Cheap, but also evens out the triangle sizes, which
+    /// moves vertices along the surface.
+    Uniform,
+    /// Neighbors are weighted with the cotangents of the angles opposite of their edge. Negative
+    /// weights of obtuse angles are clamped to zero and the weights of each vertex are normalized
+    /// to sum to one, so the step size does not depend on the size of the triangles. This is not
+    /// the exact cotangent Laplacian, but still keeps vertices in place on flat parts without
+    /// obtuse triangles.
+    Cotangent,
+}
+
+// Returns the vertices that must not move: the ones on the boundary and, if feature_angle is
+// given, the ones on edges whose faces meet at an angle larger than feature_angle.
+fn locked_vertices<S: RealField + Float>(
+    mesh: &Mesh<S>,
+    adjacency: &Adjacency,
+    feature_angle: Option<S>,
+) -> Vec<bool> {
+    let normal = |f: usize| {
+        let [a, b, c] = mesh.faces[f].map(|v| point(&mesh.vertices[v]));
+        (b - a).cross(&(c - a))
+    };
+    let mut locked = vec![false; mesh.vertices.len()];
+    for (a, b) in adjacency.edges() {
+        let faces: Vec<usize> = adjacency.edge_faces(&mesh.faces, a, b).collect();
+        let lock = match (faces.as_slice(), feature_angle) {
+            ([f, g], Some(angle)) => normal(*f).angle(&normal(*g)) > angle,
+            ([_, _], None) => false,
+            _ => true,
+        };
+        if lock {
+            locked[a] = true;
+            locked[b] = true;
+        }
+    }
+    locked
+}
+
+// Returns the weight of each directed edge in the Laplacian, normalized per vertex.
+fn weights<S: RealField + Float + From<f32>>(
+    mesh: &Mesh<S>,
+    adjacency: &Adjacency,
+    laplacian: Laplacian,
+) -> Vec<S> {
+    let mut weights = vec![S::zero(); adjacency.num_directed_edges()];
+    match laplacian {
+        Laplacian::Uniform => weights.fill(S::one()),
+        Laplacian::Cotangent => {
+            let half: S = From::from(0.5f32);
+            for f in &mesh.faces {
+                for k in 0..3 {
+                    // The angle at c is opposite of the edge a-b.
+                    let (a, b, c) = (f[k], f[(k + 1) % 3], f[(k + 2) % 3]);
+                    let [pa, pb, pc] = [a, b, c].map(|v| point(&mesh.vertices[v]));
+                    let (u, w) = (pa - pc, pb - pc);
+                    let cot = u.dot(&w) / u.cross(&w).norm();
+                    // Obtuse angles give negative weights, which make the smoothing unstable.
+                    let cot = if cot.is_finite() { Float::max(cot, S::zero()) } else { S::zero() };
+                    for (from, to) in [(a, b), (b, a)] {
+                        weights[adjacency.edge_slot(from, to).unwrap()] += cot * half;
+                    }
+                }
+            }
+        }
+    }
+    for v in 0..adjacency.num_vertices() {
+        let slots = adjacency.edge_slot_range(v);
+        let sum = weights[slots.clone()].iter().fold(S::zero(), |sum, &w| sum + w);
+        if sum > S::zero() {
+            weights[slots].iter_mut().for_each(|w| *w /= sum);
+        }
+    }
+    weights
+}
+
+impl<S: RealField + Float + From<f32>> Mesh<S> {
+    // Move every unlocked vertex by factor times its Laplacian.
+    fn laplacian_step(
+        &mut self,
+        adjacency: &Adjacency,
+        laplacian: Laplacian,
+        locked: &[bool],
+        factor: S,
+    ) {
+        let weights = weights(self, adjacency, laplacian);
+        let moved: Vec<[S; 3]> = (0..self.vertices.len())
+            .map(|v| {
+                let p = point(&self.vertices[v]);
+                if locked[v] {
+                    return self.vertices[v];
+                }
+                let delta = adjacency
+                    .neighbors(v)
+                    .iter()
+                    .zip(&weights[adjacency.edge_slot_range(v)])
+                    .fold(na::Vector3::zeros(), |sum: na::Vector3<S>, (&u, &w)| {
+                        sum + (point(&self.vertices[u]) - p) * w
+                    });
+                let p = p + delta * factor;
+                [p.x, p.y, p.z]
+            })
+            .collect();
+        self.vertices = moved;
+    }
+    /// Smooth the mesh without shrinking it (Taubin).
+    ///
+    /// Each of the `iterations` moves all vertices by `lambda` times their uniform Laplacian and
+    /// then by `mu` times the Laplacian of the result. `mu` must be negative and a bit larger in
+    /// magnitude than `lambda`, e.g. `lambda = 0.5` and `mu = -0.53`. The boundary of open meshes
+    /// is kept.
+    pub fn smooth_taubin(&mut self, iterations: usize, lambda: S, mu: S) {
+        self.smooth_taubin_with(iterations, lambda, mu, Laplacian::Uniform, None);
+    }
+    /// Like [`smooth_taubin`](Self::smooth_taubin), but with a choice of `laplacian` and
+    /// optional feature edge locking.
+    ///
+    /// If `feature_angle` is given, vertices on edges whose faces meet at an angle larger than
+    /// `feature_angle` (in radians) are not moved, so that sharp features survive.
+    pub fn smooth_taubin_with(
+        &mut self,
+        iterations: usize,
+        lambda: S,
+        mu: S,
+        laplacian: Laplacian,
+        feature_angle: Option<S>,
+    ) {
+        let adjacency = Adjacency::new(&self.faces, self.vertices.len());
+        let locked = locked_vertices(self, &adjacency, feature_angle);
+        for _ in 0..iterations {
+            self.laplacian_step(&adjacency, laplacian, &locked, lambda);
+            self.laplacian_step(&adjacency, laplacian, &locked, mu);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::Laplacian;
+    use crate::{test_shapes::Sphere, ImplicitFunction, ManifoldDualContouring, Mesh};
+    use nalgebra as na;
+
+    struct Cube;
+
+    impl ImplicitFunction<f64> for Cube {
+        fn value(&self, p: &na::Point3<f64>) -> f64 {
+            p.coords.abs().max() - 0.5
+        }
+        fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+            let mut n = na::Vector3::zeros();
+            let i = p.coords.iamax();
+            n[i] = p[i].signum();
+            n
+        }
+    }
+
+    // Returns mean and standard deviation of the distance of the vertices to the origin.
+    fn radius_stats(mesh: &Mesh<f64>) -> (f64, f64) {
+        let radii: Vec<f64> = mesh
+            .vertices
+            .iter()
+            .map(|v| na::Vector3::from(*v).norm())
+            .collect();
+        let mean = radii.iter().sum::<f64>() / radii.len() as f64;
+        let variance = radii.iter().map(|r| (r - mean).powi(2)).sum::<f64>() / radii.len() as f64;
+        (mean, variance.sqrt())
+    }
+
+    fn noisy_sphere() -> Mesh<f64> {
+        let mut mesh = ManifoldDualContouring::new(&Sphere::unit(), 0.1, 0.)
+            .tessellate()
+            .unwrap();
+        for (i, v) in mesh.vertices.iter_mut().enumerate() {
+            let noise = 1. + 0.05 * ((i * 7919 % 101) as f64 / 50. - 1.);
+            *v = v.map(|c| c * noise);
+        }
+        mesh
+    }
+
+    #[test]
+    fn taubin_keeps_volume() {
+        for laplacian in [Laplacian::Uniform, Laplacian::Cotangent] {
+            let mut mesh = noisy_sphere();
+            let (_, noise) = radius_stats(&mesh);
+            mesh.smooth_taubin_with(10, 0.5, -0.53, laplacian, None);
+            let (mean, deviation) = radius_stats(&mesh);
+            assert!(deviation < noise / 2., "{laplacian:?}: {noise} -> {deviation}");
+            assert!((mean - 1.).abs() < 0.02, "{laplacian:?}: {mean}");
+        }
+        // Plain Laplacian smoothing shrinks.
+        let mut mesh = noisy_sphere();
+        mesh.smooth_taubin(10, 0.5, 0.5);
+        assert!(radius_stats(&mesh).0 < 0.97);
+    }
+
+    #[test]
+    fn feature_edges_are_locked() {
+        let mut mesh = ManifoldDualContouring::new(&Cube, 0.1, 0.)
+            .tessellate()
+            .unwrap();
+        let original = mesh.clone();
+        mesh.smooth_taubin_with(5, 0.5, -0.53, Laplacian::Cotangent, Some(0.5));
+        let mut locked = 0;
+        for (v, o) in mesh.vertices.iter().zip(&original.vertices) {
+            let on_edge = o.iter().filter(|c| (c.abs() - 0.5).abs() < 1e-3).count() >= 2;
+            if on_edge {
+                assert_eq!(v, o);
+                locked += 1;
+            }
+        }
+        assert!(locked > 20, "{locked}");
+    }
+}
@@ -0,0 +1,280 @@
+use crate::vertex_index::Index;
+use rayon::prelude::*;
+use std::{
+    collections::HashMap,
+    hash::{BuildHasherDefault, Hasher},
+};
+
+// Bricks are BRICK_SIZE³ cells large.
+const BRICK_BITS: u32 = 3;
+const BRICK_SIZE: u32 = 1 << BRICK_BITS;
+const BRICK_CELLS: usize = (BRICK_SIZE * BRICK_SIZE * BRICK_SIZE) as usize;
+
+// Spread the lower 21 bits of x, so that there are two zero bits between each of them.
+fn spread_bits(x: u32) -> u64 {
+    let mut x = u64::from(x) & 0x1f_ffff;
+    x = (x | x << 32) & 0x1f_0000_0000_ffff;
+    x = (x | x << 16) & 0x1f_0000_ff00_00ff;
+    x = (x | x << 8) & 0x100f_00f0_0f00_f00f;
+    x = (x | x << 4) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x << 2) & 0x1249_2492_4924_9249;
+    x
+}
+
+// Inverse of spread_bits.
+fn compact_bits(x: u64) -> u32 {
+    let mut x = x & 0x1249_2492_4924_9249;
+    x = (x | x >> 2) & 0x10c3_0c30_c30c_30c3;
+    x = (x | x >> 4) & 0x100f_00f0_0f00_f00f;
+    x = (x | x >> 8) & 0x1f_0000_ff00_00ff;
+    x = (x | x >> 16) & 0x1f_0000_0000_ffff;
+    x = (x | x >> 32) & 0x1f_ffff;
+    x as u32
+}
+
+// Morton code (z-order) of p. Only the lower 21 bits of each coordinate are used.
+fn morton(p: [u32; 3]) -> u64 {
+    spread_bits(p[0]) | spread_bits(p[1]) << 1 | spread_bits(p[2]) << 2
+}
+
+fn inverse_morton(code: u64) -> [u32; 3] {
+    [
+        compact_bits(code),
+        compact_bits(code >> 1),
+        compact_bits(code >> 2),
+    ]
+}
+
+// Grid coordinates must be below this limit, as the Morton code keeps 21 bits per axis.
+pub const GRID_LIMIT: usize = 1 << 21;
+
+// Keys of a SparseGrid. A key maps to a cell in a grid of packed u32 coordinates.
+pub trait GridKey: Copy {
+    fn to_cell(self) -> [u32; 3];
+    fn from_cell(cell: [u32; 3]) -> Self;
+}
+
+impl GridKey for Index {
+    fn to_cell(self) -> [u32; 3] {
+        self.map(|i| {
+            debug_assert!(i < GRID_LIMIT, "grid index {} out of range", i);
+            i as u32
+        })
+    }
+    fn from_cell(cell: [u32; 3]) -> Self {
+        cell.map(|i| i as usize)
+    }
+}
+
+// Hasher for Morton codes. They are unique already, so a single multiplication is enough to
+// spread them over the hash table.
+#[derive(Default)]
+pub struct MortonHasher(u64);
+
+impl Hasher for MortonHasher {
+    fn finish(&self) -> u64 {
+        self.0
+    }
+    fn write(&mut self, bytes: &[u8]) {
+        for &b in bytes {
+            self.write_u64(u64::from(b));
+        }
+    }
+    fn write_u64(&mut self, x: u64) {
+        self.0 = (self.0.rotate_left(5) ^ x).wrapping_mul(0x9e37_79b9_7f4a_7c15);
+    }
+    fn write_u32(&mut self, x: u32) {
+        self.write_u64(u64::from(x));
+    }
+    fn write_usize(&mut self, x: usize) {
+        self.write_u64(x as u64);
+    }
+}
+
+pub type FastHashMap<K, V> = HashMap<K, V, BuildHasherDefault<MortonHasher>>;
+
+// A brick stores the values of the occupied cells in Morton order. occupied has a bit per cell.
+#[derive(Clone, Debug)]
+struct Brick<T> {
+    code: u64,
+    occupied: [u64; BRICK_CELLS / 64],
+    values: Vec<T>,
+}
+
+impl<T> Brick<T> {
+    // Returns whether cell is occupied and the position of its value in values.
+    fn rank(&self, cell: usize) -> (bool, usize) {
+        let word = cell / 64;
+        let bit = 1u64 << (cell % 64);
+        let below: u32 = self.occupied[..word].iter().map(|w| w.count_ones()).sum();
+        let rank = below + (self.occupied[word] & (bit - 1)).count_ones();
+        (self.occupied[word] & bit != 0, rank as usize)
+    }
+}
+
+// Block sparse grid. Cells are grouped into bricks of 8³ cells, which are found by the Morton code
+// of their position. Only occupied cells of a brick take up memory.
+// Iteration order is deterministic: bricks in order of their creation, cells within a brick in
+// Morton order.
+#[derive(Clone, Debug)]
+pub struct SparseGrid<K, T> {
+    bricks: Vec<Brick<T>>,
+    brick_map: FastHashMap<u64, usize>,
+    len: usize,
+    key: std::marker::PhantomData<K>,
+}
+
+impl<K: GridKey, T> SparseGrid<K, T> {
+    pub fn new() -> Self {
+        SparseGrid {
+            bricks: Vec::new(),
+            brick_map: FastHashMap::default(),
+            len: 0,
+            key: std::marker::PhantomData,
+        }
+    }
+    pub fn len(&self) -> usize {
+        self.len
+    }
+    // Returns the Morton code of the brick of key and the position of key within that brick.
+    fn locate(key: K) -> (u64, usize) {
+        let code = morton(key.to_cell());
+        (code >> (3 * BRICK_BITS), (code & (BRICK_CELLS as u64 - 1)) as usize)
+    }
+    pub fn get(&self, key: &K) -> Option<&T> {
+        let (code, cell) = Self::locate(*key);
+        let brick = &self.bricks[*self.brick_map.get(&code)?];
+        match brick.rank(cell) {
+            (true, rank) => Some(&brick.values[rank]),
+            (false, _) => None,
+        }
+    }
+    // Insert value at key and return the previous value, if there was one.
+    pub fn insert(&mut self, key: K, value: T) -> Option<T> {
+        let (code, cell) = Self::locate(key);
+        let num_bricks = self.bricks.len();
+        let brick_index = *self.brick_map.entry(code).or_insert(num_bricks);
+        if brick_index == num_bricks {
+            self.bricks.push(Brick {
+                code,
+                occupied: [0; BRICK_CELLS / 64],
+                values: Vec::new(),
+            });
+        }
+        let brick = &mut self.bricks[brick_index];
+        match brick.rank(cell) {
+            (true, rank) => Some(std::mem::replace(&mut brick.values[rank], value)),
+            (false, rank) => {
+                brick.occupied[cell / 64] |= 1 << (cell % 64);
+                brick.values.insert(rank, value);
+                self.len += 1;
+                None
+            }
+        }
+    }
+    // Keep only the values for which f returns true. Bricks that end up empty are dropped.
+    pub fn retain(&mut self, mut f: impl FnMut(&K, &T) -> bool) {
+        for brick in &mut self.bricks {
+            let keep: Vec<bool> = Self::brick_keys(brick)
+                .zip(brick.values.iter())
+                .map(|(k, v)| f(&k, v))
+                .collect();
+            let mut keep_value = keep.iter();
+            brick.values.retain(|_| *keep_value.next().unwrap());
+            let mut occupied = [0; BRICK_CELLS / 64];
+            let mut keep = keep.iter();
+            for cell in 0..BRICK_CELLS {
+                if brick.occupied[cell / 64] & 1 << (cell % 64) != 0 && *keep.next().unwrap() {
+                    occupied[cell / 64] |= 1 << (cell % 64);
+                }
+            }
+            brick.occupied = occupied;
+        }
+        self.bricks.retain(|b| !b.values.is_empty());
+        self.bricks.shrink_to_fit();
+        self.brick_map.clear();
+        for (i, b) in self.bricks.iter_mut().enumerate() {
+            b.values.shrink_to_fit();
+            self.brick_map.insert(b.code, i);
+        }
+        self.len = self.bricks.iter().map(|b| b.values.len()).sum();
+    }
+    fn brick_keys(brick: &Brick<T>) -> impl Iterator<Item = K> + '_ {
+        let base = brick.code << (3 * BRICK_BITS);
+        (0..BRICK_CELLS)
+            .filter(move |&cell| brick.occupied[cell / 64] & 1 << (cell % 64) != 0)
+            .map(move |cell| K::from_cell(inverse_morton(base | cell as u64)))
+    }
+    pub fn keys(&self) -> impl Iterator<Item = K> + '_ {
+        self.bricks.iter().flat_map(|b| Self::brick_keys(b))
+    }
+    pub fn iter(&self) -> impl Iterator<Item = (K, &T)> + '_ {
+        self.bricks
+            .iter()
+            .flat_map(|b| Self::brick_keys(b).zip(b.values.iter()))
+    }
+}
+
+impl<K: GridKey + Send, T: Sync> SparseGrid<K, T> {
+    // Parallel version of iter. Collecting it preserves the order of iter.
+    pub fn par_iter(&self) -> impl ParallelIterator<Item = (K, &T)> + '_ {
+        self.bricks
+            .par_iter()
+            .flat_map_iter(|b| Self::brick_keys(b).zip(b.values.iter()))
+    }
+}
+
+impl<K: GridKey, T> Default for SparseGrid<K, T> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<K: GridKey, T> std::ops::Index<&K> for SparseGrid<K, T> {
+    type Output = T;
+    fn index(&self, key: &K) -> &T {
+        self.get(key).expect("key not found in SparseGrid")
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn morton_round_trip() {
+        for p in [[0, 0, 0], [1, 2, 3], [7, 0, 5], [0x1f_ffff, 12345, 0x10_0000]] {
+            assert_eq!(inverse_morton(morton(p)), p);
+        }
+        assert_eq!(morton([1, 0, 0]), 1);
+        assert_eq!(morton([0, 1, 0]), 2);
+        assert_eq!(morton([0, 0, 1]), 4);
+        assert_eq!(morton([3, 3, 3]), 63);
+    }
+
+    #[test]
+    fn insert_get_retain() {
+        let mut grid = SparseGrid::<Index, usize>::new();
+        let mut expected = std::collections::HashMap::new();
+        for i in 0..2000usize {
+            let idx = [(i * 7) % 23, (i * 13) % 31, (i * 5) % 17];
+            assert_eq!(grid.insert(idx, i), expected.insert(idx, i));
+        }
+        assert_eq!(grid.len(), expected.len());
+        for (k, v) in &expected {
+            assert_eq!(grid.get(k), Some(v));
+        }
+        assert_eq!(grid.get(&[100, 0, 0]), None);
+        assert_eq!(grid.iter().count(), expected.len());
+        for (k, v) in grid.iter() {
+            assert_eq!(expected[&k], *v);
+        }
+
+        grid.retain(|k, _| k[0] % 2 == 0);
+        expected.retain(|k, _| k[0] % 2 == 0);
+        assert_eq!(grid.len(), expected.len());
+        for (k, v) in grid.iter() {
+            assert_eq!(expected[&k], *v);
+        }
+    }
+}
@@ -0,0 +1,314 @@
+use crate::mesh::{invalid_data, Mesh, MeshSink};
+use crate::RealField;
+use nalgebra as na;
+use std::collections::HashMap;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+
+// Size of the header of binary STL files.
+const HEADER_SIZE: usize = 80;
+
+/// Streaming writer for binary STL files.
+///
+/// STL stores the coordinates of all three corners with every triangle, so this writer keeps the
+/// vertices (as f32) in memory, but writes each triangle as soon as it is added. The triangle count
+/// in the header is patched by [`finish`](Self::finish).
+pub struct StlWriter<W: Write + Seek> {
+    writer: W,
+    start: u64,
+    vertices: Vec<[f32; 3]>,
+    num_triangles: u32,
+    // The first error that occured while writing. All later writes are skipped.
+    error: Option<io::Error>,
+}
+
+impl<W: Write + Seek> StlWriter<W> {
+    /// Create a new writer and write the STL header to writer.
+    pub fn new(mut writer: W) -> io::Result<StlWriter<W>> {
+        let start = writer.stream_position()?;
+        let mut header = [b' '; HEADER_SIZE];
+        let title = b"binary STL written by tessellation";
+        header[..title.len()].copy_from_slice(title);
+        writer.write_all(&header)?;
+        writer.write_all(&0u32.to_le_bytes())?;
+        Ok(StlWriter {
+            writer,
+            start,
+            vertices: Vec::new(),
+            num_triangles: 0,
+            error: None,
+        })
+    }
+    /// Patch the triangle count and return the underlying writer, or the first error that occured
+    /// while writing.
+    pub fn finish(mut self) -> io::Result<W> {
+        if let Some(e) = self.error {
+            return Err(e);
+        }
+        let end = self.writer.stream_position()?;
+        self.writer
+            .seek(SeekFrom::Start(self.start + HEADER_SIZE as u64))?;
+        self.writer.write_all(&self.num_triangles.to_le_bytes())?;
+        self.writer.seek(SeekFrom::Start(end))?;
+        self.writer.flush()?;
+        Ok(self.writer)
+    }
+    fn write_triangle(&mut self, f: [usize; 3]) -> io::Result<()> {
+        let v = f.map(|i| na::Vector3::from(self.vertices[i]));
+        let normal = (v[1] - v[0]).cross(&(v[2] - v[0]));
+        let normal = normal.try_normalize(0.).unwrap_or(normal);
+        let mut record = [0u8; 50];
+        for (i, c) in normal.iter().chain(v.iter().flat_map(|v| v.iter())).enumerate() {
+            record[i * 4..i * 4 + 4].copy_from_slice(&c.to_le_bytes());
+        }
+        self.writer.write_all(&record)?;
+        self.num_triangles = self
+            .num_triangles
+            .checked_add(1)
+            .ok_or_else(|| io::Error::other("too many triangles for STL"))?;
+        Ok(())
+    }
+}
+
+impl<W: Write + Seek, S> MeshSink<S> for StlWriter<W>
+where
+    f64: From<S>,
+{
+    fn add_vertex(&mut self, v: [S; 3]) -> usize {
+        self.vertices.push(v.map(|c| f64::from(c) as f32));
+        self.vertices.len() - 1
+    }
+    fn add_triangle(&mut self, f: [usize; 3]) {
+        if self.error.is_none() {
+            self.error = self.write_triangle(f).err();
+        }
+    }
+}
+
+// Returns the corners of all triangles of a binary STL file, or None if data is not one.
+fn binary_corners(data: &[u8]) -> Option<Vec<[f64; 3]>> {
+    let count = u32::from_le_bytes(data.get(HEADER_SIZE..HEADER_SIZE + 4)?.try_into().unwrap());
+    if data.len() as u64 != HEADER_SIZE as u64 + 4 + 50 * count as u64 {
+        return None;
+    }
+    let read_f32 = |offset: usize| f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap());
+    // Each record holds the normal, the three corners and two attribute bytes.
+    let corners = (0..count as usize)
+        .flat_map(|i| (0..3).map(move |k| HEADER_SIZE + 4 + i * 50 + 12 + k * 12))
+        .map(|offset| [0, 4, 8].map(|c| read_f32(offset + c) as f64))
+        .collect();
+    Some(corners)
+}
+
+// Returns the corners of all triangles of an ASCII STL file.
+fn ascii_corners(data: &[u8]) -> io::Result<Vec<[f64; 3]>> {
+    let text = std::str::from_utf8(data)
+        .ok()
+        .filter(|text| text.trim_start().starts_with("solid"))
+        .ok_or_else(|| invalid_data("neither binary nor ASCII STL"))?;
+    let mut tokens = text.split_whitespace();
+    let mut corners = Vec::new();
+    while let Some(token) = tokens.next() {
+        if token == "vertex" {
+            let mut corner = [0.; 3];
+            for c in &mut corner {
+                let token = tokens.next().unwrap_or_default();
+                *c = token
+                    .parse()
+                    .map_err(|_| invalid_data(format!("invalid STL coordinate {:?}", token)))?;
+            }
+            corners.push(corner);
+        }
+    }
+    if corners.len() % 3 != 0 {
+        return Err(invalid_data("STL facet without three vertices"));
+    }
+    Ok(corners)
+}
+
+// Merge corners closer than tolerance into one vertex. Returns the vertices and the vertex of
+// every corner. The corners are hashed into cells of size tolerance, so that only the
+// neighboring cells need to be searched.
+fn weld(corners: &[[f64; 3]], tolerance: f64) -> (Vec<[f64; 3]>, Vec<usize>) {
+    let mut vertices: Vec<[f64; 3]> = Vec::new();
+    let mut cells: HashMap<[i64; 3], Vec<usize>> = HashMap::new();
+    let mut exact: HashMap<[u64; 3], usize> = HashMap::new();
+    let mut indexes = Vec::with_capacity(corners.len());
+    for &corner in corners {
+        if tolerance <= 0. {
+            // Adding zero turns -0 into 0.
+            let key = corner.map(|c| (c + 0.).to_bits());
+            let index = *exact.entry(key).or_insert(vertices.len());
+            if index == vertices.len() {
+                vertices.push(corner);
+            }
+            indexes.push(index);
+            continue;
+        }
+        let cell = corner.map(|c| (c / tolerance).floor() as i64);
+        let distance = |v: &[f64; 3]| (0..3).map(|i| (v[i] - corner[i]).powi(2)).sum::<f64>();
+        let mut found = None;
+        'search: for dx in -1..=1 {
+            for dy in -1..=1 {
+                for dz in -1..=1 {
+                    let neighbor = [cell[0] + dx, cell[1] + dy, cell[2] + dz];
+                    for &v in cells.get(&neighbor).into_iter().flatten() {
+                        if distance(&vertices[v]) <= tolerance * tolerance {
+                            found = Some(v);
+                            break 'search;
+                        }
+                    }
+                }
+            }
+        }
+        let index = found.unwrap_or_else(|| {
+            vertices.push(corner);
+            cells.entry(cell).or_default().push(vertices.len() - 1);
+            vertices.len() - 1
+        });
+        indexes.push(index);
+    }
+    (vertices, indexes)
+}
+
+impl<S: RealField> Mesh<S>
+where
+    f64: From<S>,
+{
+    /// Read a binary or ASCII STL file.
+    ///
+    /// STL stores the corners of every triangle separately. Corners closer than `weld_tolerance`
+    /// are merged into one vertex and triangles that collapse by this are dropped. With a
+    /// tolerance of zero, only identical corners are merged.
+    pub fn read_stl<R: Read>(mut reader: R, weld_tolerance: S) -> io::Result<Mesh<S>> {
+        let mut data = Vec::new();
+        reader.read_to_end(&mut data)?;
+        // Binary files may start with "solid" as well, so check the size first.
+        let corners = match binary_corners(&data) {
+            Some(corners) => corners,
+            None => ascii_corners(&data)?,
+        };
+        let (vertices, indexes) = weld(&corners, f64::from(weld_tolerance));
+        let mut mesh = Mesh {
+            vertices: vertices.iter().map(|v| v.map(na::convert)).collect(),
+            faces: indexes.chunks_exact(3).map(|f| [f[0], f[1], f[2]]).collect(),
+            ..Mesh::default()
+        };
+        let degenerate: Vec<bool> = mesh
+            .faces
+            .iter()
+            .map(|f| f[0] == f[1] || f[1] == f[2] || f[2] == f[0])
+            .collect();
+        mesh.remove_faces(&degenerate);
+        Ok(mesh)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::io::Cursor;
+
+    fn read_f32(data: &[u8], offset: usize) -> f32 {
+        f32::from_le_bytes(data[offset..offset + 4].try_into().unwrap())
+    }
+
+    #[test]
+    fn triangles_and_count() {
+        let mut stl = StlWriter::new(Cursor::new(Vec::new())).unwrap();
+        for v in [[0., 0., 0.], [1., 0., 0.], [0., 1., 0.], [0., 0., 1.]] {
+            MeshSink::<f64>::add_vertex(&mut stl, v);
+        }
+        MeshSink::<f64>::add_triangle(&mut stl, [0, 1, 2]);
+        MeshSink::<f64>::add_triangle(&mut stl, [0, 3, 1]);
+        let data = stl.finish().unwrap().into_inner();
+        assert_eq!(data.len(), 84 + 2 * 50);
+        assert_eq!(u32::from_le_bytes(data[80..84].try_into().unwrap()), 2);
+        // Normal and third corner of the first triangle.
+        assert_eq!(read_f32(&data, 84 + 8), 1.);
+        assert_eq!(read_f32(&data, 84 + 40), 1.);
+        // Normal of the second triangle.
+        assert_eq!(read_f32(&data, 134 + 4), 1.);
+    }
+
+    fn octahedron() -> Mesh<f64> {
+        Mesh {
+            vertices: vec![
+                [1., 0., 0.],
+                [-1., 0., 0.],
+                [0., 1., 0.],
+                [0., -1., 0.],
+                [0., 0., 1.],
+                [0., 0., -1.],
+            ],
+            faces: vec![
+                [0, 2, 4],
+                [2, 1, 4],
+                [1, 3, 4],
+                [3, 0, 4],
+                [2, 0, 5],
+                [1, 2, 5],
+                [3, 1, 5],
+                [0, 3, 5],
+            ],
+            ..Mesh::default()
+        }
+    }
+
+    #[test]
+    fn binary_round_trip() {
+        let original = octahedron();
+        let mut stl = StlWriter::new(Cursor::new(Vec::new())).unwrap();
+        for v in &original.vertices {
+            stl.add_vertex(*v);
+        }
+        for f in &original.faces {
+            MeshSink::<f64>::add_triangle(&mut stl, *f);
+        }
+        let data = stl.finish().unwrap().into_inner();
+        let mesh = Mesh::<f64>::read_stl(data.as_slice(), 0.).unwrap();
+        assert_eq!(mesh.faces.len(), 8);
+        assert_eq!(mesh.vertices.len(), 6);
+        mesh.is_closed().unwrap();
+        for f in &mesh.faces {
+            let corners = f.map(|v| mesh.vertices[v]);
+            assert!(original.faces.iter().any(|g| g.map(|v| original.vertices[v]) == corners));
+        }
+    }
+
+    #[test]
+    fn ascii_welding() {
+        let stl = "solid square\n\
+                   facet normal 0 0 1\n\
+                   outer loop\n\
+                   vertex 0 0 0\n\
+                   vertex 1 0 0\n\
+                   vertex 1 1 0\n\
+                   endloop\n\
+                   endfacet\n\
+                   facet normal 0 0 1\n\
+                   outer loop\n\
+                   vertex 0.0001 0 0\n\
+                   vertex 1 1.0001 0\n\
+                   vertex 0 1 0\n\
+                   endloop\n\
+                   endfacet\n\
+                   facet normal 0 0 1\n\
+                   outer loop\n\
+                   vertex 0 0 0\n\
+                   vertex 0.0001 0 0\n\
+                   vertex 0 1 0\n\
+                   endloop\n\
+                   endfacet\n\
+                   endsolid square\n";
+        let exact = Mesh::<f64>::read_stl(stl.as_bytes(), 0.).unwrap();
+        assert_eq!(exact.vertices.len(), 6);
+        assert_eq!(exact.faces.len(), 3);
+        // Welding collapses the third facet.
+        let welded = Mesh::<f64>::read_stl(stl.as_bytes(), 0.001).unwrap();
+        assert_eq!(welded.vertices.len(), 4);
+        assert_eq!(welded.faces, vec![[0, 1, 2], [0, 2, 3]]);
+
+        assert!(Mesh::<f64>::read_stl("solid x\nvertex 0 0\n".as_bytes(), 0.).is_err());
+        assert!(Mesh::<f64>::read_stl(&[0u8; 90][..], 0.).is_err());
+    }
+}
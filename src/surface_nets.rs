@@ -0,0 +1,116 @@
+use crate::{
+    manifold_dual_contouring::{
+        is_inside, push_quad, quad_cells, Edge, EdgeIndex, ManifoldDualContouring, ProgressEvent,
+    },
+    mesh::Mesh,
+    vertex_index::Index,
+    AsUSize, ImplicitFunction, RealField,
+};
+use nalgebra as na;
+use num_traits::Float;
+use std::collections::HashMap;
+
+/// Naive surface nets extractor.
+///
+/// Surface nets place exactly one vertex per grid cell at the average of the edge crossings in
+/// that cell. There is no QEF solve and no octree, which makes them a lot cheaper than
+/// [`ManifoldDualContouring`](crate::ManifoldDualContouring). Sharp features are rounded off and
+/// the mesh is not simplified, so this is mostly useful for fast previews.
+#[derive(Clone)]
+pub struct SurfaceNets<'a, S: RealField> {
+    mdc: ManifoldDualContouring<'a, S>,
+}
+
+impl<'a, S: From<f32> + RealField + Float + AsUSize> SurfaceNets<'a, S> {
+    /// Constructor
+    /// f: function to tessellate
+    /// res: resolution
+    pub fn new(f: &'a dyn ImplicitFunction<S>, res: S) -> SurfaceNets<'a, S> {
+        SurfaceNets {
+            mdc: ManifoldDualContouring::new(f, res, From::from(0f32)),
+        }
+    }
+    /// Tessellate the given function.
+    pub fn tessellate(&mut self) -> Option<Mesh<S>> {
+        self.tessellate_with_progress(|_| {})
+    }
+
+    /// Tessellate, calling `progress` after each pipeline stage with a [`ProgressEvent`].
+    /// The octree stages of [`ManifoldDualContouring`](crate::ManifoldDualContouring) are skipped.
+    pub fn tessellate_with_progress(
+        &mut self,
+        mut progress: impl FnMut(ProgressEvent),
+    ) -> Option<Mesh<S>> {
+        self.mdc.find_grid();
+        progress(ProgressEvent::BoundsFound);
+        self.mdc.sample_surface(&mut progress);
+        let mut mesh = Mesh::default();
+        let mut cell_vertices = HashMap::new();
+        let edge_keys: Vec<EdgeIndex> = self.mdc.edge_grid.borrow().keys().collect();
+        let total = edge_keys.len();
+        for (i, &edge_index) in edge_keys.iter().enumerate() {
+            progress(ProgressEvent::GeneratingQuad { done: i + 1, total });
+            let mut p = Vec::with_capacity(4);
+            for (_, cell) in quad_cells(edge_index) {
+                let point_index = *cell_vertices
+                    .entry(cell)
+                    .or_insert_with(|| self.add_cell_vertex(cell, &mut mesh));
+                p.push(point_index);
+            }
+            let reverse = is_inside(self.mdc.value_grid[&edge_index.index]);
+            let tag = self.mdc.edge_grid.borrow()[&edge_index].tag;
+            push_quad(&mut mesh, &mut p, reverse, tag);
+        }
+        progress(ProgressEvent::Done {
+            face_count: mesh.faces.len(),
+        });
+        Some(mesh)
+    }
+
+    // Add the vertex of cell idx to mesh - which is the average of all edge crossings in the cell.
+    fn add_cell_vertex(&self, idx: Index, mesh: &mut Mesh<S>) -> usize {
+        let edge_grid = self.mdc.edge_grid.borrow();
+        let mut sum: na::Vector3<S> = na::Vector3::zeros();
+        let mut num = 0;
+        for edge in 0..12 {
+            let edge_index = EdgeIndex {
+                edge: Edge::from_usize(edge),
+                index: idx,
+            };
+            if let Some(crossing) = edge_grid.get(&edge_index.base()) {
+                sum += crossing.plane.p.coords;
+                num += 1;
+            }
+        }
+        debug_assert!(num > 0, "cell {:?} has no edge crossings", idx);
+        let num_as_s: S = From::from(num as f32);
+        let mean = sum / num_as_s;
+        mesh.vertices.push([mean.x, mean.y, mean.z]);
+        mesh.vertices.len() - 1
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::SurfaceNets;
+    use crate::{test_shapes::Sphere, ImplicitFunction};
+    use nalgebra as na;
+
+    #[test]
+    fn sphere() -> Result<(), Box<dyn std::error::Error>> {
+        let sphere = Sphere::unit();
+        let mut sn = SurfaceNets::new(&sphere, 0.2);
+        let mesh = sn.tessellate().unwrap();
+        assert!(!mesh.faces.is_empty());
+        mesh.is_closed()?;
+        for v in &mesh.vertices {
+            let p = na::Point3::new(v[0], v[1], v[2]);
+            assert!(
+                sphere.value(&p).abs() < 0.2,
+                "vertex {v:?} is {:.4} from the surface",
+                sphere.value(&p).abs(),
+            );
+        }
+        Ok(())
+    }
+}
@@ -0,0 +1,29 @@
+// Implicit functions shared by the tests of all modules.
+
+use crate::ImplicitFunction;
+use nalgebra as na;
+
+/// Sphere SDF centred at `center` with the given `radius`.
+pub struct Sphere {
+    pub center: na::Point3<f64>,
+    pub radius: f64,
+}
+
+impl Sphere {
+    pub fn new(center: na::Point3<f64>, radius: f64) -> Self {
+        Sphere { center, radius }
+    }
+    /// Sphere with radius 1 at the origin.
+    pub fn unit() -> Self {
+        Sphere::new(na::Point3::origin(), 1.0)
+    }
+}
+
+impl ImplicitFunction<f64> for Sphere {
+    fn value(&self, p: &na::Point3<f64>) -> f64 {
+        (p - self.center).norm() - self.radius
+    }
+    fn normal(&self, p: &na::Point3<f64>) -> na::Vector3<f64> {
+        (p - self.center).normalize()
+    }
+}